@@ -4,8 +4,6 @@ pub fn bin_pack(items: &[u32], _capacity: u32) -> u32 {
     let mut res = 0;
     let mut remaining_capacity = _capacity;
 
-    // TODO: return assignment
-
     for item in items {
         // If this item can't fit in current bin
         if *item > remaining_capacity {
@@ -18,13 +16,45 @@ pub fn bin_pack(items: &[u32], _capacity: u32) -> u32 {
     res
 }
 
+// next-first heuristic for bin packing that returns the bin of each item.
+// Items larger than the capacity get a bin of their own.
+pub fn bin_pack_assignment(items: &[u32], capacity: u32) -> Vec<u32> {
+    let mut bin = 0;
+    let mut remaining_capacity = capacity;
+    let mut assignment = Vec::with_capacity(items.len());
+
+    for (i, item) in items.iter().enumerate() {
+        // If this item can't fit in current bin
+        if *item > remaining_capacity && i > 0 {
+            bin += 1;
+            remaining_capacity = capacity.saturating_sub(*item);
+        } else {
+            remaining_capacity = remaining_capacity.saturating_sub(*item);
+        }
+        assignment.push(bin);
+    }
+    assignment
+}
+
 #[cfg(test)]
 mod tests {
-    use crate::bin_pack::bin_pack;
+    use crate::bin_pack::{bin_pack, bin_pack_assignment};
 
     #[test]
     fn instance_g4g() {
         let weight = vec![2, 5, 4, 7, 1, 3, 8];
         assert_eq!(4, bin_pack(&weight, 10));
     }
+
+    #[test]
+    fn assignment_g4g() {
+        let weight = vec![2, 5, 4, 7, 1, 3, 8];
+        assert_eq!(vec![0, 0, 1, 2, 2, 3, 4], bin_pack_assignment(&weight, 10));
+    }
+
+    #[test]
+    fn oversized_items() {
+        let weight = vec![12, 3, 11, 2, 2];
+        assert_eq!(vec![0, 1, 2, 3, 3], bin_pack_assignment(&weight, 10));
+    }
 }
@@ -1,11 +1,15 @@
 use std::{fmt::Display, ops::RangeInclusive};
 
-use clap::Parser;
+use clap::{ArgEnum, Parser};
+use toolbox_rs::partition::MAXIMUM_LEVEL;
 
-static RECURSION_RANGE: RangeInclusive<u8> = 1..=31;
+// chipper keeps the cap of 32 bit partition ids, as its partition files store
+// them. Deeper hierarchies need PartitionID64 through the library.
+static RECURSION_RANGE: RangeInclusive<u8> = 1..=MAXIMUM_LEVEL;
 static BALANCE_RANGE: RangeInclusive<f64> = 0. ..=0.5;
 
-/// Checks whether the recursion range is within the expected range of (1, 31].
+/// Checks whether the recursion range is within the expected range of
+/// [1, MAXIMUM_LEVEL].
 pub fn recursion_depth_in_range(s: &str) -> Result<u8, String> {
     let recursion_depth: u8 = s.parse().map_err(|_| format!("`{}` isn't a number", s))?;
     if RECURSION_RANGE.contains(&recursion_depth) {
@@ -33,6 +37,26 @@ pub fn balance_factor_in_range(s: &str) -> Result<f64, String> {
     }
 }
 
+/// Checks whether the number of cells is positive
+pub fn cell_count_in_range(s: &str) -> Result<usize, String> {
+    let cells: usize = s.parse().map_err(|_| format!("`{}` isn't a number", s))?;
+    if cells > 0 {
+        Ok(cells)
+    } else {
+        Err("number of cells must be positive".to_string())
+    }
+}
+
+#[derive(ArgEnum, Clone, Copy, Debug)]
+pub enum Partitioner {
+    InertialFlow,
+    FlowCutter,
+    Multilevel,
+    Spectral,
+    ZOrder,
+    Hilbert,
+}
+
 #[derive(Parser, Debug)]
 #[clap(author, version, about, long_about = None)]
 pub struct Arguments {
@@ -44,9 +68,9 @@ pub struct Arguments {
     #[clap(short, long, action)]
     pub graph: String,
 
-    /// path to the input coordinates
+    /// path to the input coordinates; not needed by the multilevel and spectral partitioners
     #[clap(short, long, action)]
-    pub coordinates: String,
+    pub coordinates: Option<String>,
 
     /// path to the cut-csv file
     #[clap(short = 'o', long, default_value_t = String::new(), action)]
@@ -56,15 +80,61 @@ pub struct Arguments {
     #[clap(short, long, default_value_t = String::new(), action)]
     pub assignment_csv: String,
 
-    /// balance factor to use
+    /// partitioner to bisect cells with
+    #[clap(long, arg_enum, default_value_t = Partitioner::InertialFlow)]
+    pub partitioner: Partitioner,
+
+    /// number of evenly spaced directions along which cells are cut
+    #[clap(short, long, value_parser = clap::value_parser!(u16).range(1..), default_value_t = 4)]
+    pub directions: u16,
+
+    /// use the edge weights of the graph as capacities of the cuts; not
+    /// supported by the flow-cutter and space-filling curve partitioners
+    #[clap(long, action)]
+    pub weighted: bool,
+
+    /// additionally cut along the principal axis of each cell's coordinates
+    #[clap(long, action)]
+    pub principal_axis: bool,
+
+    /// refine each cut with Fiduccia-Mattheyses
+    #[clap(long, action)]
+    pub refine: bool,
+
+    /// balance factor to use; the minimum balance of a cut for flow-cutter
     #[clap(short, long, value_parser = balance_factor_in_range, default_value_t = 0.25)]
     pub b_factor: f64,
 
     /// depth of recursive partitioning; off by one from the level of a node
-    /// since the root node has level 1, e.g. depths of 1 gives cells on level 2
+    /// since the root node has level 1, e.g. depths of 1 gives cells on level 2.
+    /// At most 31, the deepest level of the 32 bit partition ids in the output
     #[clap(short, long, value_parser=recursion_depth_in_range, default_value_t = 1)]
     pub recursion_depth: u8,
 
+    /// path to the JSON report of partition quality metrics
+    #[clap(long, default_value_t = String::new(), action)]
+    pub metrics_json: String,
+
+    /// path to the output file with the cells, cell hierarchy and boundary
+    /// nodes of all levels
+    #[clap(long, default_value_t = String::new(), action)]
+    pub multi_level_partition: String,
+
+    /// path to the csv file of node separators; separates cells by node
+    /// separators instead of edge cuts, which are computed by node splitting
+    /// with inertial flow
+    #[clap(long, default_value_t = String::new(), action)]
+    pub separator_csv: String,
+
+    /// bisect until no cell is larger than this size instead of to a fixed depth
+    #[clap(long, conflicts_with = "cells", action)]
+    pub maximum_cell_size: Option<usize>,
+
+    /// number of cells to partition into by uneven bisections instead of
+    /// bisecting to a fixed depth
+    #[clap(short = 'k', long, value_parser = cell_count_in_range)]
+    pub cells: Option<usize>,
+
     /// path to the output file with partition ids
     #[clap(short, long, default_value_t = String::new(), action)]
     pub partition_file: String,
@@ -89,9 +159,31 @@ impl Display for Arguments {
         if !self.cut_csv.is_empty() {
             writeln!(f, "cut csv: {}", self.cut_csv)?;
         }
+        if !self.metrics_json.is_empty() {
+            writeln!(f, "metrics json: {}", self.metrics_json)?;
+        }
+        if !self.multi_level_partition.is_empty() {
+            writeln!(f, "multi-level partition: {}", self.multi_level_partition)?;
+        }
+        if !self.separator_csv.is_empty() {
+            writeln!(f, "separator csv: {}", self.separator_csv)?;
+        }
         writeln!(f, "graph: {}", self.graph)?;
-        writeln!(f, "coordinates: {}", self.coordinates)?;
-        writeln!(f, "recursion depth: {}", self.recursion_depth)?;
+        if let Some(coordinates) = &self.coordinates {
+            writeln!(f, "coordinates: {}", coordinates)?;
+        }
+        writeln!(f, "partitioner: {:?}", self.partitioner)?;
+        writeln!(f, "directions: {}", self.directions)?;
+        writeln!(f, "weighted: {}", self.weighted)?;
+        writeln!(f, "principal axis: {}", self.principal_axis)?;
+        writeln!(f, "refine: {}", self.refine)?;
+        if let Some(maximum_cell_size) = self.maximum_cell_size {
+            writeln!(f, "maximum cell size: {}", maximum_cell_size)?;
+        } else if let Some(cells) = self.cells {
+            writeln!(f, "cells: {}", cells)?;
+        } else {
+            writeln!(f, "recursion depth: {}", self.recursion_depth)?;
+        }
         writeln!(f, "balance factor: {}", self.b_factor)?;
         writeln!(f, "minimum_cell_size: {}", self.minimum_cell_size)
     }
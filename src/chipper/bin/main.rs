@@ -2,19 +2,25 @@ mod command_line;
 mod serialize;
 
 use env_logger::Env;
-use itertools::Itertools;
-
 use indicatif::{ProgressBar, ProgressStyle};
-use log::{debug, info};
-use rayon::prelude::*;
-use std::sync::{atomic::AtomicI32, Arc};
-use toolbox_rs::io;
-use toolbox_rs::unsafe_slice::UnsafeSlice;
+use itertools::Itertools;
+use log::{error, info};
+use toolbox_rs::partition::{recursive_separation, Bisector, RecursiveBisectionConfig};
 use toolbox_rs::{
-    inertial_flow::{self, flow_cmp, FlowResult},
-    partition::PartitionID,
+    edge::TrivialEdge,
+    io,
+    space_filling_curve::{self, Curve},
+};
+use {
+    command_line::{Arguments, Partitioner},
+    serialize::write_results,
 };
-use {command_line::Arguments, serialize::write_results};
+
+/// Reports invalid input or arguments and exits with a failure code
+fn exit_with(message: String) -> ! {
+    error!("{message}");
+    std::process::exit(1);
+}
 
 fn main() {
     env_logger::Builder::from_env(Env::default().default_filter_or("info")).init();
@@ -40,121 +46,138 @@ fn main() {
             .unwrap();
     }
 
-    let edges = io::read_graph_into_trivial_edges(&args.graph);
-    let coordinates = io::read_coordinates(&args.coordinates);
+    if args.weighted
+        && matches!(
+            args.partitioner,
+            Partitioner::FlowCutter | Partitioner::ZOrder | Partitioner::Hilbert
+        )
+    {
+        exit_with(format!(
+            "--weighted is not supported by the {:?} partitioner",
+            args.partitioner
+        ));
+    }
+
+    if args.coordinates.is_none()
+        && !matches!(
+            args.partitioner,
+            Partitioner::Multilevel | Partitioner::Spectral
+        )
+    {
+        exit_with(format!(
+            "the {:?} partitioner needs coordinates",
+            args.partitioner
+        ));
+    }
+
+    let curve = matches!(args.partitioner, Partitioner::ZOrder | Partitioner::Hilbert);
+    if curve && args.maximum_cell_size == Some(0) {
+        exit_with(format!(
+            "--maximum-cell-size must be positive for the {:?} partitioner",
+            args.partitioner
+        ));
+    }
+
+    let separators = !args.separator_csv.is_empty();
+    if separators && curve {
+        exit_with(format!(
+            "--separator-csv is not supported by the {:?} partitioner",
+            args.partitioner
+        ));
+    }
+
+    let weighted_edges = io::read_graph_into_weighted_edges(&args.graph);
+    if args.weighted {
+        // capacities of cuts must not be negative
+        if let Some(edge) = weighted_edges.iter().find(|edge| edge.data < 0) {
+            exit_with(format!(
+                "edge ({}, {}) has negative weight {}, which is not a valid capacity",
+                edge.source, edge.target, edge.data
+            ));
+        }
+    }
+    let edges = weighted_edges
+        .iter()
+        .map(|edge| TrivialEdge {
+            source: edge.source,
+            target: edge.target,
+        })
+        .collect_vec();
+    let coordinates = match &args.coordinates {
+        Some(filename) => io::read_coordinates(filename),
+        None => Vec::new(),
+    };
     info!(
         "loaded {} edges and {} coordinates",
         edges.len(),
         coordinates.len()
     );
-
-    // enqueue initial job for partitioning of the root node into job queue
-    let id_vector = (0..coordinates.len()).collect_vec();
-    let job = (edges.clone(), id_vector);
-    let mut current_job_queue = vec![job];
+    if curve && coordinates.is_empty() {
+        exit_with(format!(
+            "the {:?} partitioner needs coordinates, but none were loaded",
+            args.partitioner
+        ));
+    }
 
     let sty = ProgressStyle::default_spinner()
         .template("{spinner:.green} [{elapsed_precise}] {wide_bar:.green/yellow} {msg}")
         .unwrap()
         .progress_chars("#>-");
-
-    let mut current_level = 0;
-    let mut partition_ids_vec = vec![PartitionID::root(); coordinates.len()];
-    let partition_ids = UnsafeSlice::new(&mut partition_ids_vec);
-
-    while !current_job_queue.is_empty() && current_level < args.recursion_depth {
-        let pb = ProgressBar::new(current_job_queue.len() as u64);
-        pb.set_style(sty.clone());
-
-        let next_job_queue = current_job_queue
-            .par_iter_mut()
-            .enumerate()
-            .flat_map(|(id, job)| {
-                pb.set_message(format!("cell #{}", id));
-                pb.inc(1);
-
-                // we use the count of coordinates as an upper bound to the cut size
-                let upper_bound = Arc::new(AtomicI32::new(job.1.len().try_into().unwrap()));
-                // run inertial flow on all four axes
-                let best_max_flow = (0..4)
-                    .into_par_iter()
-                    .map(|axis| -> FlowResult {
-                        inertial_flow::sub_step(
-                            &job.0,
-                            &job.1,
-                            &coordinates,
-                            axis,
-                            args.b_factor,
-                            upper_bound.clone(),
-                        )
-                    })
-                    .min_by(flow_cmp);
-
-                let result = best_max_flow.unwrap();
-                debug!(
-                    "best max-flow: {}, balance: {:.3}",
-                    result.flow, result.balance
-                );
-
-                debug!("partitioning and assigning ids for all nodes");
-
-                (result.left_ids).iter().for_each(|id| unsafe {
-                    partition_ids.get(*id).inplace_left_child();
-                });
-                (result.right_ids).iter().for_each(|id| unsafe {
-                    partition_ids.get(*id).inplace_right_child();
-                });
-
-                // partition edge and node id sets for the next iteration
-                debug!("generating next level edges");
-                // TODO: don't copy, but partition in place
-                let (left_edges, right_edges): (Vec<_>, Vec<_>) = job
-                    .0
-                    .iter()
-                    .filter(|edge| unsafe {
-                        partition_ids.get(edge.source) == partition_ids.get(edge.target)
-                    })
-                    .partition(|edge| unsafe { partition_ids.get(edge.source).is_left_child() });
-                debug!("generating next level ids");
-
-                // iterate on both halves
-                let mut next_jobs = Vec::new();
-                if result.left_ids.len() > args.minimum_cell_size {
-                    next_jobs.push((left_edges, result.left_ids));
-                } else {
-                    let level_difference = (args.recursion_depth - current_level - 1) as usize;
-                    for i in &result.left_ids {
-                        unsafe {
-                            partition_ids
-                                .get(*i)
-                                .inplace_leftmost_descendant(level_difference);
-                        }
+    let pb = ProgressBar::new(0);
+    pb.set_style(sty);
+
+    let (partition_ids, separators) = match args.partitioner {
+        Partitioner::ZOrder | Partitioner::Hilbert => {
+            // slice the curve into cells of the size that bisection would yield
+            let cell_size = match (args.maximum_cell_size, args.cells) {
+                (Some(maximum_cell_size), _) => maximum_cell_size,
+                (None, Some(cells)) => coordinates.len().div_ceil(cells),
+                (None, None) => coordinates.len().div_ceil(1 << args.recursion_depth),
+            };
+            info!("slicing space-filling curve into cells of at most {cell_size} nodes");
+            let curve = match args.partitioner {
+                Partitioner::Hilbert => Curve::Hilbert,
+                _ => Curve::ZOrder,
+            };
+            let partition_ids = space_filling_curve::partition(&coordinates, curve, cell_size);
+            (partition_ids, Vec::new())
+        }
+        partitioner => {
+            let config = RecursiveBisectionConfig {
+                bisector: match partitioner {
+                    Partitioner::InertialFlow => Bisector::InertialFlow,
+                    Partitioner::FlowCutter => Bisector::FlowCutter,
+                    Partitioner::Multilevel => Bisector::Multilevel,
+                    Partitioner::Spectral => Bisector::Spectral,
+                    Partitioner::ZOrder | Partitioner::Hilbert => unreachable!(),
+                },
+                recursion_depth: args.recursion_depth,
+                balance_factor: args.b_factor,
+                minimum_cell_size: args.minimum_cell_size,
+                maximum_cell_size: args.maximum_cell_size,
+                cells: args.cells,
+                directions: args.directions as usize,
+                principal_axis: args.principal_axis,
+                refine: args.refine,
+                separators,
+                progress: Some(Box::new({
+                    let pb = pb.clone();
+                    move |level, finished, total| {
+                        pb.set_length(total as u64);
+                        pb.set_position(finished as u64);
+                        pb.set_message(format!("level {level}"));
                     }
-                }
-                if result.right_ids.len() > args.minimum_cell_size {
-                    next_jobs.push((right_edges, result.right_ids));
-                } else {
-                    let level_difference = (args.recursion_depth - current_level - 1) as usize;
-                    for i in &result.right_ids {
-                        unsafe {
-                            partition_ids
-                                .get(*i)
-                                .inplace_leftmost_descendant(level_difference);
-                        }
-                    }
-                }
-                next_jobs
-            })
-            .collect();
-        current_level += 1;
-        pb.finish_with_message(format!("level {current_level} done"));
-        current_job_queue = next_job_queue;
-    }
-
-    write_results(&args, &partition_ids_vec, &coordinates, &edges);
-
-    for id in &partition_ids_vec {
-        debug_assert_eq!(id.level(), args.recursion_depth);
-    }
+                })),
+            };
+            if args.weighted {
+                recursive_separation(&weighted_edges, &coordinates, &config)
+            } else {
+                recursive_separation(&edges, &coordinates, &config)
+            }
+        }
+    };
+    pb.finish_with_message("done");
+
+    write_results(&args, &partition_ids, &separators, &coordinates, &edges);
     info!("done.");
 }
@@ -1,10 +1,16 @@
 use bincode::serialize_into;
-use log::info;
+use log::{info, warn};
 use std::{
     fs::File,
     io::{BufWriter, Write},
 };
-use toolbox_rs::{edge::TrivialEdge, geometry::primitives::FPCoordinate, partition::PartitionID};
+use toolbox_rs::{
+    edge::TrivialEdge,
+    geometry::primitives::FPCoordinate,
+    multi_level_partition::MultiLevelPartition,
+    partition::{CellSeparator, PartitionID},
+    partition_metrics,
+};
 
 use crate::command_line::Arguments;
 
@@ -85,19 +91,77 @@ pub fn binary_partition_file(partition_file: &str, partition_ids: &[PartitionID]
     serialize_into(&mut f, &partition_ids).unwrap();
 }
 
+pub fn metrics_json(filename: &str, edges: &[TrivialEdge], partition_ids: &[PartitionID]) {
+    let metrics = partition_metrics::compute(edges, partition_ids);
+    for level in &metrics.levels {
+        info!(
+            "level {}: {} cells, {} cut edges, imbalance {:.3}, {} disconnected cells",
+            level.level,
+            level.number_of_cells,
+            level.cut_edges,
+            level.imbalance,
+            level.disconnected_cells
+        );
+    }
+    let file = BufWriter::new(File::create(filename).expect("output file cannot be opened"));
+    serde_json::to_writer_pretty(file, &metrics).expect("error writing file");
+}
+
+/// Writes one line per separator node with the id and level of the cell that
+/// it separates
+pub fn separator_csv(filename: &str, separators: &[CellSeparator<u32>]) {
+    let mut file = BufWriter::new(File::create(filename).expect("output file cannot be opened"));
+    writeln!(file, "cell, level, node").expect("error writing file");
+    for separator in separators {
+        for node in &separator.separator_ids {
+            writeln!(
+                file,
+                "{}, {}, {node}",
+                separator.cell,
+                separator.cell.level()
+            )
+            .expect("error writing file");
+        }
+    }
+    file.flush().expect("error writing file");
+}
+
 pub fn write_results(
     args: &Arguments,
     partition_ids: &[PartitionID],
+    separators: &[CellSeparator<u32>],
     coordinates: &[FPCoordinate],
     edges: &[TrivialEdge],
 ) {
-    if !args.assignment_csv.is_empty() {
-        info!("writing partition csv into: {}", args.assignment_csv);
-        assignment_csv(&args.assignment_csv, partition_ids, coordinates);
+    if coordinates.is_empty() {
+        // the csv files carry the geometry of cells and cuts
+        if !args.assignment_csv.is_empty() || !args.cut_csv.is_empty() {
+            warn!("no coordinates given, skipping csv output");
+        }
+    } else {
+        if !args.assignment_csv.is_empty() {
+            info!("writing partition csv into: {}", args.assignment_csv);
+            assignment_csv(&args.assignment_csv, partition_ids, coordinates);
+        }
+        if !args.cut_csv.is_empty() {
+            info!("writing cut csv to {}", &args.cut_csv);
+            cut_csv(&args.cut_csv, edges, partition_ids, coordinates);
+        }
+    }
+    if !args.metrics_json.is_empty() {
+        info!("writing metrics json to {}", &args.metrics_json);
+        metrics_json(&args.metrics_json, edges, partition_ids);
+    }
+    if !args.multi_level_partition.is_empty() {
+        info!(
+            "writing multi-level partition to {}",
+            &args.multi_level_partition
+        );
+        MultiLevelPartition::new(edges, partition_ids).write(&args.multi_level_partition);
     }
-    if !args.cut_csv.is_empty() {
-        info!("writing cut csv to {}", &args.cut_csv);
-        cut_csv(&args.cut_csv, edges, partition_ids, coordinates);
+    if !args.separator_csv.is_empty() {
+        info!("writing separators to {}", &args.separator_csv);
+        separator_csv(&args.separator_csv, separators);
     }
     if !args.partition_file.is_empty() {
         info!("writing partition ids to {}", &args.partition_file);
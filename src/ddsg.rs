@@ -1,13 +1,26 @@
+use std::{
+    cmp::max,
+    fmt::Display,
+    fs::File,
+    io::{BufWriter, Write},
+};
+
 use itertools::Itertools;
 use log::info;
 
-use crate::{edge::InputEdge, geometry::primitives::FPCoordinate, graph::NodeID, io::read_lines};
+use crate::{
+    edge::InputEdge,
+    geometry::primitives::FPCoordinate,
+    graph::NodeID,
+    io::{numbered_lines, parse_token, ParseError},
+};
 
 pub enum WeightType {
     Unit,
     Original,
 }
 
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
 pub enum Direction {
     Both = 0,
     Forward = 1,
@@ -15,6 +28,24 @@ pub enum Direction {
     Closed = 3,
 }
 
+impl Direction {
+    /// Appends the directed edges along which a road segment can be traversed
+    pub fn explode<T: Eq + Clone>(
+        &self,
+        source: NodeID,
+        target: NodeID,
+        data: T,
+        edges: &mut Vec<InputEdge<T>>,
+    ) {
+        if matches!(self, Direction::Both | Direction::Forward) {
+            edges.push(InputEdge::new(source, target, data.clone()));
+        }
+        if matches!(self, Direction::Both | Direction::Reverse) {
+            edges.push(InputEdge::new(target, source, data));
+        }
+    }
+}
+
 impl TryFrom<i32> for Direction {
     type Error = ();
 
@@ -29,118 +60,251 @@ impl TryFrom<i32> for Direction {
     }
 }
 
-pub fn read_graph<T: std::cmp::Eq + From<i32>>(
+pub fn read_graph<T: std::cmp::Eq + Clone + From<i32>>(
     filename: &str,
     weight_type: WeightType,
-) -> Vec<InputEdge<T>> {
+) -> Result<Vec<InputEdge<T>>, ParseError> {
     let mut edges = Vec::new();
 
-    let mut lines = read_lines(filename).expect("could not load graph file");
+    let mut lines = numbered_lines(filename)?;
+    let missing_header = || ParseError::new(filename, 0, "missing header");
 
-    let first_line = lines.next().unwrap().unwrap();
-    if first_line != "d" {
-        return edges;
+    let (_, first_line) = lines.next().ok_or_else(missing_header)??;
+    if first_line.trim() != "d" {
+        return Err(ParseError::new(filename, 1, "not a DDSG graph file"));
     }
 
-    let second_line = lines.next().unwrap().unwrap();
-    let sizes = second_line
-        .get(..)
-        .unwrap_or("")
-        .split_ascii_whitespace()
-        .collect_vec();
-    info!("expecting {} nodes and {} edges", sizes[0], sizes[1]);
+    let (number, second_line) = lines.next().ok_or_else(missing_header)??;
+    let sizes = second_line.split_ascii_whitespace().collect_vec();
+    let number_of_nodes: usize = parse_token(filename, number, &sizes, 0, "number of nodes")?;
+    let number_of_edges: usize = parse_token(filename, number, &sizes, 1, "number of edges")?;
+    info!("expecting {number_of_nodes} nodes and {number_of_edges} edges");
 
     let mut input_edge_counter = 0;
 
     // load dimacs graph and coordinates
     for line in lines {
-        let line = line.unwrap();
+        let (number, line) = line?;
         // IDSTARTNODE IDDESTNODE WEIGHT BLOCKEDDIR DISTANCE TIME
-        let tokens = line.get(..).unwrap_or("").split_whitespace().collect_vec();
-        if tokens.len() != 4 {
+        let tokens = line.split_whitespace().collect_vec();
+        if tokens.is_empty() {
             continue;
         }
-        let source = tokens[0].parse::<NodeID>().unwrap();
-        let target = tokens[1].parse::<NodeID>().unwrap();
+        let source = parse_token::<NodeID>(filename, number, &tokens, 0, "source")?;
+        let target = parse_token::<NodeID>(filename, number, &tokens, 1, "target")?;
+        let data = parse_token::<i32>(filename, number, &tokens, 2, "weight")?;
+        let direction = parse_token::<i32>(filename, number, &tokens, 3, "direction")?;
+        let direction = Direction::try_from(direction).map_err(|_| {
+            ParseError::new(filename, number, format!("invalid direction {direction}"))
+        })?;
+        if source >= number_of_nodes || target >= number_of_nodes {
+            return Err(ParseError::new(
+                filename,
+                number,
+                format!("node id out of range 0..{number_of_nodes}"),
+            ));
+        }
 
         // avoid eigenloops
         if source == target {
             continue;
         }
-
-        let data = tokens[2].parse::<i32>().unwrap();
-        let direction = Direction::try_from(tokens[3].parse::<i32>().unwrap()).unwrap();
         input_edge_counter += 1;
 
-        match direction {
-            Direction::Both => {
-                edges.push(InputEdge::<T> {
-                    source,
-                    target,
-                    data: match &weight_type {
-                        WeightType::Unit => T::from(1),
-                        WeightType::Original => T::from(data),
-                    },
-                });
-                edges.push(InputEdge::<T> {
-                    target,
-                    source,
-                    data: match &weight_type {
-                        WeightType::Unit => T::from(1),
-                        WeightType::Original => T::from(data),
-                    },
-                });
-            }
-            Direction::Forward => {
-                edges.push(InputEdge::<T> {
-                    source,
-                    target,
-                    data: match &weight_type {
-                        WeightType::Unit => T::from(1),
-                        WeightType::Original => T::from(data),
-                    },
-                });
-            }
-            Direction::Reverse => {
-                edges.push(InputEdge::<T> {
-                    target,
-                    source,
-                    data: match &weight_type {
-                        WeightType::Unit => T::from(1),
-                        WeightType::Original => T::from(data),
-                    },
-                });
-            }
-            Direction::Closed => {
-                // closed in both directions, thus ignore
-            }
-        }
+        let data = match &weight_type {
+            WeightType::Unit => T::from(1),
+            WeightType::Original => T::from(data),
+        };
+        direction.explode(source, target, data, &mut edges);
     }
     info!(
         "exploded {input_edge_counter} input edges into {} directed edges",
         edges.len()
     );
-    edges
+    Ok(edges)
 }
 
-pub fn read_coordinates(filename: &str) -> Vec<FPCoordinate> {
-    let mut lines = read_lines(filename).expect("could not load coordinates file");
-    let first_line = lines.next().unwrap().unwrap();
-    let coordinate_count = first_line.parse::<usize>().unwrap();
+pub fn read_coordinates(filename: &str) -> Result<Vec<FPCoordinate>, ParseError> {
+    let mut lines = numbered_lines(filename)?;
+    let (number, first_line) = lines
+        .next()
+        .ok_or_else(|| ParseError::new(filename, 0, "missing header"))??;
+    let coordinate_count: usize = parse_token(
+        filename,
+        number,
+        &[first_line.trim()],
+        0,
+        "number of coordinates",
+    )?;
     info!("expecting {coordinate_count} coordinates");
     let mut coordinates = Vec::with_capacity(coordinate_count);
     for line in lines {
-        let line = line.unwrap();
-        let tokens = line.get(..).unwrap_or("").split_whitespace().collect_vec();
-        let count = tokens[0].parse::<usize>().unwrap();
-        assert_eq!(count, coordinates.len());
-
-        let lon = tokens[1].parse::<f64>().unwrap() / 100_000.;
-        let lat = tokens[2].parse::<f64>().unwrap() / 100_000.;
-        coordinates.push(FPCoordinate::new_from_lat_lon(lat, lon));
+        let (number, line) = line?;
+        let tokens = line.split_whitespace().collect_vec();
+        if tokens.is_empty() {
+            continue;
+        }
+        let id = parse_token::<usize>(filename, number, &tokens, 0, "node id")?;
+        if id != coordinates.len() {
+            return Err(ParseError::new(
+                filename,
+                number,
+                format!("expected node {}, found {id}", coordinates.len()),
+            ));
+        }
+
+        // coordinates are given in units of 1e-5 degrees
+        let lon = parse_token::<f64>(filename, number, &tokens, 1, "longitude")?;
+        let lat = parse_token::<f64>(filename, number, &tokens, 2, "latitude")?;
+        coordinates.push(FPCoordinate::new(
+            (lat * 10.).round() as i32,
+            (lon * 10.).round() as i32,
+        ));
+    }
+    if coordinate_count != coordinates.len() {
+        return Err(ParseError::new(
+            filename,
+            0,
+            format!(
+                "expected {coordinate_count} coordinates, found {}",
+                coordinates.len()
+            ),
+        ));
     }
-    assert_eq!(coordinate_count, coordinates.len());
     info!("loaded {coordinate_count} coordinates");
 
-    coordinates
+    Ok(coordinates)
+}
+
+/// Writes a graph in DDSG format. Each edge is written as a forward edge, so
+/// reading the file yields the same directed edges. The number of nodes is
+/// derived from the largest node id.
+pub fn write_graph<T: Eq + Display>(filename: &str, edges: &[InputEdge<T>]) -> std::io::Result<()> {
+    let number_of_nodes = edges
+        .iter()
+        .map(|edge| max(edge.source, edge.target) + 1)
+        .max()
+        .unwrap_or(0);
+    let mut file = BufWriter::new(File::create(filename)?);
+    writeln!(file, "d")?;
+    writeln!(file, "{number_of_nodes} {}", edges.len())?;
+    for edge in edges {
+        writeln!(
+            file,
+            "{} {} {} {}",
+            edge.source,
+            edge.target,
+            edge.data,
+            Direction::Forward as i32
+        )?;
+    }
+    file.flush()
+}
+
+/// Writes coordinates in units of 1e-5 degrees, i.e. the last digit of the
+/// fixed-point coordinates is rounded off.
+pub fn write_coordinates(filename: &str, coordinates: &[FPCoordinate]) -> std::io::Result<()> {
+    let mut file = BufWriter::new(File::create(filename)?);
+    writeln!(file, "{}", coordinates.len())?;
+    for (id, coordinate) in coordinates.iter().enumerate() {
+        writeln!(
+            file,
+            "{id} {} {}",
+            (coordinate.lon as f64 / 10.).round(),
+            (coordinate.lat as f64 / 10.).round()
+        )?;
+    }
+    file.flush()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use crate::{
+        ddsg::{read_coordinates, read_graph, write_coordinates, write_graph, WeightType},
+        edge::InputEdge,
+        geometry::primitives::FPCoordinate,
+        io::ParseError,
+        test_utils::temp_file,
+    };
+
+    #[test]
+    fn directions() {
+        let path = temp_file("ddsg_directions.ddsg");
+        fs::write(&path, "d\n4 4\n0 1 5 0\n1 2 6 1\n2 3 7 2 100 10\n3 0 8 3\n").unwrap();
+        let edges = read_graph::<i32>(path.to_str().unwrap(), WeightType::Original).unwrap();
+        fs::remove_file(&path).unwrap();
+        assert_eq!(
+            edges,
+            vec![
+                InputEdge::new(0, 1, 5),
+                InputEdge::new(1, 0, 5),
+                InputEdge::new(1, 2, 6),
+                InputEdge::new(3, 2, 7),
+            ]
+        );
+    }
+
+    #[test]
+    fn malformed_input() {
+        let path = temp_file("ddsg_malformed_input.ddsg");
+        let filename = path.to_str().unwrap();
+        for (content, line, reason) in [
+            ("x\n", 1, "not a DDSG graph file"),
+            ("d\n2\n", 2, "missing number of edges"),
+            ("d\n2 1\n0 1 5 4\n", 3, "invalid direction 4"),
+            ("d\n2 1\n0 2 5 0\n", 3, "node id out of range 0..2"),
+        ] {
+            fs::write(&path, content).unwrap();
+            let error = read_graph::<i32>(filename, WeightType::Unit).unwrap_err();
+            assert_eq!(error, ParseError::new(filename, line, reason));
+        }
+
+        fs::write(&path, "2\n0 100000 200000\n").unwrap();
+        let error = read_coordinates(filename).unwrap_err();
+        assert_eq!(
+            error,
+            ParseError::new(filename, 0, "expected 2 coordinates, found 1")
+        );
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn round_trip() {
+        // opposite edges of different weights are written as two forward edges
+        let edges = vec![
+            InputEdge::new(0, 1, 7),
+            InputEdge::new(1, 0, 3),
+            InputEdge::new(1, 4, 2),
+            InputEdge::new(4, 2, 9),
+        ];
+        let coordinates = vec![
+            FPCoordinate::new(52_520_010, 13_404_950),
+            FPCoordinate::new(-33_868_820, 151_209_290),
+        ];
+        let (graph, co) = (
+            temp_file("ddsg_round_trip.ddsg"),
+            temp_file("ddsg_round_trip.xyz"),
+        );
+        write_graph(graph.to_str().unwrap(), &edges).unwrap();
+        write_coordinates(co.to_str().unwrap(), &coordinates).unwrap();
+        let reread = read_graph::<i32>(graph.to_str().unwrap(), WeightType::Original);
+        let reread_coordinates = read_coordinates(co.to_str().unwrap());
+        fs::remove_file(&graph).unwrap();
+        fs::remove_file(&co).unwrap();
+
+        assert_eq!(reread.unwrap(), edges);
+        assert_eq!(reread_coordinates.unwrap(), coordinates);
+    }
+
+    #[test]
+    fn coordinate_rounding() {
+        // 397e-5 degrees would be truncated to 3969e-6 degrees
+        let path = temp_file("ddsg_coordinate_rounding.xyz");
+        fs::write(&path, "1\n0 397 785\n").unwrap();
+        let coordinates = read_coordinates(path.to_str().unwrap()).unwrap();
+        fs::remove_file(&path).unwrap();
+        assert_eq!(coordinates, vec![FPCoordinate::new(7850, 3970)]);
+    }
 }
@@ -1,51 +1,81 @@
+use std::{
+    cmp::max,
+    fmt::Display,
+    fs::File,
+    io::{BufWriter, Write},
+};
+
 use itertools::Itertools;
 use log::{debug, info};
 
-use crate::{edge::InputEdge, geometry::primitives::FPCoordinate, graph::NodeID, io::read_lines};
+use crate::{
+    edge::InputEdge,
+    geometry::primitives::FPCoordinate,
+    graph::NodeID,
+    io::{numbered_lines, parse_token, ParseError},
+    max_flow::ResidualCapacity,
+};
 
 pub enum WeightType {
     Unit,
     Original,
 }
 
+/// Parses a node id, which DIMACS defines to be consecutive and starting at 1,
+/// and renumbers it to start at zero.
+fn parse_node_id(
+    filename: &str,
+    line: usize,
+    tokens: &[&str],
+    index: usize,
+) -> Result<NodeID, ParseError> {
+    parse_token::<NodeID>(filename, line, tokens, index, "node id")?
+        .checked_sub(1)
+        .ok_or_else(|| ParseError::new(filename, line, "node ids start at 1"))
+}
+
 pub fn read_graph<T: std::cmp::Eq + From<i32>>(
     filename: &str,
     weight_type: WeightType,
-) -> Vec<InputEdge<T>> {
+) -> Result<Vec<InputEdge<T>>, ParseError> {
     let mut comment_count = 0;
     let mut problem_count = 0;
     let mut edges = Vec::new();
 
     // load dimacs graph and coordinates
-    for line in read_lines(filename).expect("could not load graph file") {
-        let line = line.unwrap();
-        match line.chars().next().unwrap() {
-            'c' => {
+    for line in numbered_lines(filename)? {
+        let (number, line) = line?;
+        let tokens = line.split_whitespace().collect_vec();
+        match line.chars().next() {
+            Some('c') => {
                 comment_count += 1;
                 debug!("{}", line.get(2..).unwrap_or(""));
             }
-            'p' => {
+            Some('p') => {
                 problem_count += 1;
-                let sizes = line
-                    .get(5..)
-                    .unwrap_or("")
-                    .split_ascii_whitespace()
-                    .collect_vec();
-                info!("expecting {} nodes and {} edges", sizes[0], sizes[1]);
-                edges.reserve(sizes[0].parse::<usize>().unwrap());
+                // p sp <nodes> <edges>
+                let number_of_nodes: usize =
+                    parse_token(filename, number, &tokens, 2, "number of nodes")?;
+                let number_of_edges: usize =
+                    parse_token(filename, number, &tokens, 3, "number of edges")?;
+                info!("expecting {number_of_nodes} nodes and {number_of_edges} edges");
+                edges.reserve(number_of_edges);
             }
-            'a' => {
-                let tokens = line.get(2..).unwrap_or("").split_whitespace().collect_vec();
-                if tokens.len() != 3 {
-                    continue;
+            Some('a') => {
+                if tokens.len() != 4 {
+                    return Err(ParseError::new(
+                        filename,
+                        number,
+                        format!("expected 3 fields in arc, found {}", tokens.len() - 1),
+                    ));
                 }
-                let source = tokens[0].parse::<NodeID>().unwrap();
-                let target = tokens[1].parse::<NodeID>().unwrap();
+                let source = parse_node_id(filename, number, &tokens, 1)?;
+                let target = parse_node_id(filename, number, &tokens, 2)?;
+                let data: i32 = parse_token(filename, number, &tokens, 3, "weight")?;
                 // avoid eigenloops
                 if source == target {
                     continue;
                 }
-                let data = tokens[2].parse::<i32>().unwrap();
 
                 edges.push(InputEdge::<T> {
                     source,
@@ -62,40 +92,40 @@ pub fn read_graph<T: std::cmp::Eq + From<i32>>(
     debug!("graph file comment count: {comment_count}");
     debug!("graph file problem count: {problem_count}");
 
-    info!("renumbering source and target in edge list");
-    for mut edge in &mut edges {
-        // the DIMACS format defines numbering to be consecutive and starting at 1.
-        edge.source -= 1;
-        edge.target -= 1;
-    }
-
-    edges
+    Ok(edges)
 }
 
-pub fn read_coordinates(filename: &str) -> Vec<FPCoordinate> {
+pub fn read_coordinates(filename: &str) -> Result<Vec<FPCoordinate>, ParseError> {
     let mut coordinates = Vec::new();
     let mut comment_count = 0;
     let mut problem_count = 0;
-    for line in read_lines(filename).expect("could not load coordinates file") {
-        let line = line.unwrap();
-        match line.chars().next().unwrap() {
-            'c' => {
+    for line in numbered_lines(filename)? {
+        let (number, line) = line?;
+        let tokens = line.split_whitespace().collect_vec();
+        match line.chars().next() {
+            Some('c') => {
                 comment_count += 1;
                 debug!("{}", line.get(2..).unwrap_or(""));
             }
-            'p' => {
+            Some('p') => {
                 problem_count += 1;
-                let size = line.get(12..).unwrap_or("").parse::<usize>().unwrap();
+                // p aux sp co <nodes>
+                let size: usize = parse_token(filename, number, &tokens, 4, "number of nodes")?;
                 info!("expecting {size} coordinates");
                 coordinates.reserve(size);
             }
-            'v' => {
-                let tokens = line.get(2..).unwrap_or("").split_whitespace().collect_vec();
-                let id = tokens[0].parse::<NodeID>().unwrap();
-                let lon = tokens[1].parse::<i32>().unwrap();
-                let lat = tokens[2].parse::<i32>().unwrap();
+            Some('v') => {
+                let id = parse_node_id(filename, number, &tokens, 1)?;
+                let lon = parse_token(filename, number, &tokens, 2, "longitude")?;
+                let lat = parse_token(filename, number, &tokens, 3, "latitude")?;
+                if id != coordinates.len() {
+                    return Err(ParseError::new(
+                        filename,
+                        number,
+                        format!("expected node {}, found {}", coordinates.len() + 1, id + 1),
+                    ));
+                }
                 coordinates.push(FPCoordinate::new(lat, lon));
-                debug_assert!(coordinates.len() == id);
             }
             _ => {}
         }
@@ -103,5 +133,487 @@ pub fn read_coordinates(filename: &str) -> Vec<FPCoordinate> {
     debug!("coordinates file comment count: {comment_count}");
     debug!("coordinates file problem count: {problem_count}");
 
-    coordinates
+    Ok(coordinates)
+}
+
+/// Writes a graph as a DIMACS shortest path problem. The number of nodes is
+/// derived from the largest node id.
+pub fn write_graph<T: Eq + Display>(filename: &str, edges: &[InputEdge<T>]) -> std::io::Result<()> {
+    let number_of_nodes = edges
+        .iter()
+        .map(|edge| max(edge.source, edge.target) + 1)
+        .max()
+        .unwrap_or(0);
+    let mut file = BufWriter::new(File::create(filename)?);
+    writeln!(file, "p sp {number_of_nodes} {}", edges.len())?;
+    for edge in edges {
+        writeln!(
+            file,
+            "a {} {} {}",
+            edge.source + 1,
+            edge.target + 1,
+            edge.data
+        )?;
+    }
+    file.flush()
+}
+
+pub fn write_coordinates(filename: &str, coordinates: &[FPCoordinate]) -> std::io::Result<()> {
+    let mut file = BufWriter::new(File::create(filename)?);
+    writeln!(file, "p aux sp co {}", coordinates.len())?;
+    for (id, coordinate) in coordinates.iter().enumerate() {
+        writeln!(file, "v {} {} {}", id + 1, coordinate.lon, coordinate.lat)?;
+    }
+    file.flush()
+}
+
+/// A max-flow problem instance as defined by the DIMACS challenge format, i.e.
+/// a `p max` problem line, `n <id> s|t` designators and `a <u> <v> <cap>` arcs.
+/// Node ids are renumbered to start at zero.
+pub struct MaxFlowInstance {
+    pub number_of_nodes: usize,
+    pub source: NodeID,
+    pub target: NodeID,
+    pub edges: Vec<InputEdge<ResidualCapacity>>,
+}
+
+/// Edge data of a min-cost flow arc, i.e. a lower and upper bound on the flow
+/// and the cost per unit of flow.
+#[derive(Clone, Copy, Debug, Default, Eq, Ord, PartialEq, PartialOrd)]
+pub struct CostCapacity {
+    pub lower_bound: i32,
+    pub capacity: i32,
+    pub cost: i32,
+}
+
+/// A min-cost flow problem instance as defined by the DIMACS challenge format,
+/// i.e. a `p min` problem line, `n <id> <supply>` descriptors and arcs of the
+/// form `a <u> <v> <low> <cap> <cost>`. Node ids are renumbered to start at zero.
+pub struct MinCostFlowInstance {
+    pub number_of_nodes: usize,
+    pub supplies: Vec<(NodeID, i32)>,
+    pub edges: Vec<InputEdge<CostCapacity>>,
+}
+
+impl MinCostFlowInstance {
+    /// Returns the nodes with positive supply
+    pub fn sources(&self) -> Vec<NodeID> {
+        self.supplies
+            .iter()
+            .filter(|(_, supply)| *supply > 0)
+            .map(|(node, _)| *node)
+            .collect()
+    }
+
+    /// Returns the nodes with negative supply, i.e. with a demand
+    pub fn sinks(&self) -> Vec<NodeID> {
+        self.supplies
+            .iter()
+            .filter(|(_, supply)| *supply < 0)
+            .map(|(node, _)| *node)
+            .collect()
+    }
+
+    /// Returns the arcs with their upper capacity only, e.g. to be fed into a
+    /// max-flow solver.
+    pub fn capacity_edges(&self) -> Vec<InputEdge<ResidualCapacity>> {
+        self.edges
+            .iter()
+            .map(|edge| InputEdge::new(edge.source, edge.target, edge.data.capacity.into()))
+            .collect()
+    }
+}
+
+pub fn read_max_flow_instance(filename: &str) -> Result<MaxFlowInstance, ParseError> {
+    let mut number_of_nodes = 0;
+    let mut source = NodeID::MAX;
+    let mut target = NodeID::MAX;
+    let mut edges = Vec::new();
+
+    for line in numbered_lines(filename)? {
+        let (number, line) = line?;
+        let tokens = line.split_whitespace().collect_vec();
+        match tokens.first() {
+            Some(&"c") => {
+                debug!("{}", line.get(2..).unwrap_or(""));
+            }
+            Some(&"p") => {
+                if tokens.get(1) != Some(&"max") {
+                    return Err(ParseError::new(filename, number, "not a max-flow problem"));
+                }
+                number_of_nodes = parse_token(filename, number, &tokens, 2, "number of nodes")?;
+                let number_of_edges: usize =
+                    parse_token(filename, number, &tokens, 3, "number of edges")?;
+                info!("expecting {number_of_nodes} nodes and {number_of_edges} edges");
+                edges.reserve(number_of_edges);
+            }
+            Some(&"n") => {
+                let id = parse_node_id(filename, number, &tokens, 1)?;
+                match tokens.get(2) {
+                    Some(&"s") => source = id,
+                    Some(&"t") => target = id,
+                    designator => {
+                        return Err(ParseError::new(
+                            filename,
+                            number,
+                            format!("unknown node designator {designator:?}"),
+                        ))
+                    }
+                }
+            }
+            Some(&"a") => {
+                let source = parse_node_id(filename, number, &tokens, 1)?;
+                let target = parse_node_id(filename, number, &tokens, 2)?;
+                let capacity = parse_token(filename, number, &tokens, 3, "capacity")?;
+                // avoid eigenloops
+                if source == target {
+                    continue;
+                }
+                edges.push(InputEdge::new(
+                    source,
+                    target,
+                    ResidualCapacity::new(capacity),
+                ));
+            }
+            _ => {}
+        }
+    }
+    if source == NodeID::MAX {
+        return Err(ParseError::new(filename, 0, "no source designated"));
+    }
+    if target == NodeID::MAX {
+        return Err(ParseError::new(filename, 0, "no target designated"));
+    }
+    info!(
+        "loaded {} arcs, source {source}, target {target}",
+        edges.len()
+    );
+
+    Ok(MaxFlowInstance {
+        number_of_nodes,
+        source,
+        target,
+        edges,
+    })
+}
+
+pub fn read_min_cost_flow_instance(filename: &str) -> Result<MinCostFlowInstance, ParseError> {
+    let mut number_of_nodes = 0;
+    let mut supplies = Vec::new();
+    let mut edges = Vec::new();
+
+    for line in numbered_lines(filename)? {
+        let (number, line) = line?;
+        let tokens = line.split_whitespace().collect_vec();
+        match tokens.first() {
+            Some(&"c") => {
+                debug!("{}", line.get(2..).unwrap_or(""));
+            }
+            Some(&"p") => {
+                if tokens.get(1) != Some(&"min") {
+                    return Err(ParseError::new(
+                        filename,
+                        number,
+                        "not a min-cost flow problem",
+                    ));
+                }
+                number_of_nodes = parse_token(filename, number, &tokens, 2, "number of nodes")?;
+                let number_of_edges: usize =
+                    parse_token(filename, number, &tokens, 3, "number of edges")?;
+                info!("expecting {number_of_nodes} nodes and {number_of_edges} edges");
+                edges.reserve(number_of_edges);
+            }
+            Some(&"n") => {
+                let id = parse_node_id(filename, number, &tokens, 1)?;
+                let supply = parse_token(filename, number, &tokens, 2, "supply")?;
+                supplies.push((id, supply));
+            }
+            Some(&"a") => {
+                let source = parse_node_id(filename, number, &tokens, 1)?;
+                let target = parse_node_id(filename, number, &tokens, 2)?;
+                let data = CostCapacity {
+                    lower_bound: parse_token(filename, number, &tokens, 3, "lower bound")?,
+                    capacity: parse_token(filename, number, &tokens, 4, "capacity")?,
+                    cost: parse_token(filename, number, &tokens, 5, "cost")?,
+                };
+                // avoid eigenloops
+                if source == target {
+                    continue;
+                }
+                edges.push(InputEdge::new(source, target, data));
+            }
+            _ => {}
+        }
+    }
+    info!(
+        "loaded {} arcs and {} supply/demand nodes",
+        edges.len(),
+        supplies.len()
+    );
+
+    Ok(MinCostFlowInstance {
+        number_of_nodes,
+        supplies,
+        edges,
+    })
+}
+
+pub fn write_max_flow_instance(filename: &str, instance: &MaxFlowInstance) -> std::io::Result<()> {
+    let mut file = BufWriter::new(File::create(filename)?);
+    writeln!(
+        file,
+        "p max {} {}",
+        instance.number_of_nodes,
+        instance.edges.len()
+    )?;
+    writeln!(file, "n {} s", instance.source + 1)?;
+    writeln!(file, "n {} t", instance.target + 1)?;
+    for edge in &instance.edges {
+        writeln!(
+            file,
+            "a {} {} {}",
+            edge.source + 1,
+            edge.target + 1,
+            edge.data.capacity
+        )?;
+    }
+    file.flush()
+}
+
+pub fn write_min_cost_flow_instance(
+    filename: &str,
+    instance: &MinCostFlowInstance,
+) -> std::io::Result<()> {
+    let mut file = BufWriter::new(File::create(filename)?);
+    writeln!(
+        file,
+        "p min {} {}",
+        instance.number_of_nodes,
+        instance.edges.len()
+    )?;
+    for (node, supply) in &instance.supplies {
+        writeln!(file, "n {} {supply}", node + 1)?;
+    }
+    for edge in &instance.edges {
+        writeln!(
+            file,
+            "a {} {} {} {} {}",
+            edge.source + 1,
+            edge.target + 1,
+            edge.data.lower_bound,
+            edge.data.capacity,
+            edge.data.cost
+        )?;
+    }
+    file.flush()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use crate::{
+        dimacs::{
+            read_coordinates, read_graph, read_max_flow_instance, read_min_cost_flow_instance,
+            write_coordinates, write_graph, write_max_flow_instance, write_min_cost_flow_instance,
+            CostCapacity, MaxFlowInstance, MinCostFlowInstance, WeightType,
+        },
+        dinic::Dinic,
+        edge::InputEdge,
+        geometry::primitives::FPCoordinate,
+        io::ParseError,
+        max_flow::MaxFlow,
+        test_utils::{clr_edges, temp_file},
+    };
+
+    #[test]
+    fn max_flow_round_trip() {
+        let path = temp_file("dimacs_max_flow_round_trip.max");
+        fs::write(
+            &path,
+            "c CLR example\n\
+             p max 6 10\n\
+             n 1 s\n\
+             n 6 t\n\
+             a 1 2 16\n\
+             a 1 3 13\n\
+             a 2 3 10\n\
+             a 2 4 12\n\
+             a 3 2 4\n\
+             a 3 5 14\n\
+             a 4 3 9\n\
+             a 4 6 20\n\
+             a 5 4 7\n\
+             a 5 6 4\n",
+        )
+        .unwrap();
+
+        let instance = read_max_flow_instance(path.to_str().unwrap()).unwrap();
+        assert_eq!(instance.number_of_nodes, 6);
+        assert_eq!(instance.source, 0);
+        assert_eq!(instance.target, 5);
+        assert_eq!(instance.edges, clr_edges());
+
+        write_max_flow_instance(path.to_str().unwrap(), &instance).unwrap();
+        let reread = read_max_flow_instance(path.to_str().unwrap()).unwrap();
+        fs::remove_file(&path).unwrap();
+        assert_eq!(instance.edges, reread.edges);
+        assert_eq!(instance.source, reread.source);
+        assert_eq!(instance.target, reread.target);
+
+        let MaxFlowInstance {
+            source,
+            target,
+            edges,
+            ..
+        } = reread;
+        let mut max_flow_solver = Dinic::from_edge_list(edges, source, target);
+        max_flow_solver.run();
+        assert_eq!(23, max_flow_solver.max_flow().unwrap());
+    }
+
+    #[test]
+    fn min_cost_flow_round_trip() {
+        let instance = MinCostFlowInstance {
+            number_of_nodes: 4,
+            supplies: vec![(0, 4), (3, -4)],
+            edges: vec![
+                InputEdge::new(
+                    0,
+                    1,
+                    CostCapacity {
+                        lower_bound: 0,
+                        capacity: 4,
+                        cost: 2,
+                    },
+                ),
+                InputEdge::new(
+                    0,
+                    2,
+                    CostCapacity {
+                        lower_bound: 0,
+                        capacity: 2,
+                        cost: 2,
+                    },
+                ),
+                InputEdge::new(
+                    1,
+                    2,
+                    CostCapacity {
+                        lower_bound: 0,
+                        capacity: 2,
+                        cost: 1,
+                    },
+                ),
+                InputEdge::new(
+                    1,
+                    3,
+                    CostCapacity {
+                        lower_bound: 1,
+                        capacity: 3,
+                        cost: 3,
+                    },
+                ),
+                InputEdge::new(
+                    2,
+                    3,
+                    CostCapacity {
+                        lower_bound: 0,
+                        capacity: 5,
+                        cost: 1,
+                    },
+                ),
+            ],
+        };
+
+        let path = temp_file("dimacs_min_cost_flow_round_trip.min");
+        write_min_cost_flow_instance(path.to_str().unwrap(), &instance).unwrap();
+        let reread = read_min_cost_flow_instance(path.to_str().unwrap()).unwrap();
+        fs::remove_file(&path).unwrap();
+
+        assert_eq!(instance.number_of_nodes, reread.number_of_nodes);
+        assert_eq!(instance.supplies, reread.supplies);
+        assert_eq!(instance.edges, reread.edges);
+        assert_eq!(reread.sources(), vec![0]);
+        assert_eq!(reread.sinks(), vec![3]);
+
+        let mut max_flow_solver = Dinic::from_edge_list(reread.capacity_edges(), 0, 3);
+        max_flow_solver.run();
+        assert_eq!(6, max_flow_solver.max_flow().unwrap());
+    }
+
+    #[test]
+    fn graph_and_coordinates() {
+        let graph = temp_file("dimacs_graph_and_coordinates.gr");
+        fs::write(&graph, "c graph\n\np sp 3 3\na 1 2 7\na 2 3 5\na 3 3 1\n").unwrap();
+        let edges = read_graph::<i32>(graph.to_str().unwrap(), WeightType::Original).unwrap();
+        assert_eq!(
+            edges,
+            vec![InputEdge::new(0, 1, 7), InputEdge::new(1, 2, 5)]
+        );
+
+        let coordinates = temp_file("dimacs_graph_and_coordinates.co");
+        fs::write(&coordinates, "p aux sp co 2\nv 1 10 20\nv 2 30 40\n").unwrap();
+        let result = read_coordinates(coordinates.to_str().unwrap());
+        fs::remove_file(&graph).unwrap();
+        fs::remove_file(&coordinates).unwrap();
+        assert_eq!(
+            result.unwrap(),
+            vec![FPCoordinate::new(20, 10), FPCoordinate::new(40, 30)]
+        );
+    }
+
+    #[test]
+    fn malformed_input() {
+        let path = temp_file("dimacs_malformed_input.gr");
+        let filename = path.to_str().unwrap();
+        for (content, line, reason) in [
+            ("p sp 2 1\na 1 x 3\n", 2, "invalid node id 'x'"),
+            ("p sp 2 1\na 1 2\n", 2, "expected 3 fields in arc, found 2"),
+            ("p sp 2 1\n\na 0 1 3\n", 3, "node ids start at 1"),
+            ("p sp 2\n", 1, "missing number of edges"),
+        ] {
+            fs::write(&path, content).unwrap();
+            let error = read_graph::<i32>(filename, WeightType::Unit).unwrap_err();
+            assert_eq!(error, ParseError::new(filename, line, reason));
+        }
+
+        fs::write(&path, "p max 2 1\nn 1 s\na 1 2 3\n").unwrap();
+        let error = read_max_flow_instance(filename).err().unwrap();
+        assert_eq!(error, ParseError::new(filename, 0, "no target designated"));
+        fs::remove_file(&path).unwrap();
+
+        let error = read_graph::<i32>(filename, WeightType::Unit).unwrap_err();
+        assert_eq!(error.line, 0);
+    }
+
+    #[test]
+    fn graph_round_trip() {
+        // node ids are shifted by one, and coordinates keep all their digits
+        let edges = vec![
+            InputEdge::new(0, 5, 12),
+            InputEdge::new(5, 0, 12),
+            InputEdge::new(2, 1, 4),
+            InputEdge::new(3, 5, 1),
+        ];
+        let coordinates = vec![
+            FPCoordinate::new(52_520_008, 13_404_954),
+            FPCoordinate::new(-33_868_820, 151_209_295),
+        ];
+        let (graph, co) = (
+            temp_file("dimacs_round_trip.gr"),
+            temp_file("dimacs_round_trip.co"),
+        );
+        write_graph(graph.to_str().unwrap(), &edges).unwrap();
+        write_coordinates(co.to_str().unwrap(), &coordinates).unwrap();
+        let reread = read_graph::<i32>(graph.to_str().unwrap(), WeightType::Original);
+        let reread_coordinates = read_coordinates(co.to_str().unwrap());
+        let header = fs::read_to_string(&graph).unwrap();
+        fs::remove_file(&graph).unwrap();
+        fs::remove_file(&co).unwrap();
+
+        assert_eq!(header, "p sp 6 4\na 1 6 12\na 6 1 12\na 3 2 4\na 4 6 1\n");
+        assert_eq!(reread.unwrap(), edges);
+        assert_eq!(reread_coordinates.unwrap(), coordinates);
+    }
 }
@@ -9,7 +9,7 @@
 use crate::{
     edge::{Edge, InputEdge},
     graph::{Graph, NodeID},
-    max_flow::{MaxFlow, ResidualCapacity},
+    max_flow::{check_instance, MaxFlow, MaxFlowError, ResidualCapacity},
     static_graph::StaticGraph,
 };
 use bitvec::vec::BitVec;
@@ -27,7 +27,7 @@ use std::{
 pub struct Dinic {
     residual_graph: StaticGraph<ResidualCapacity>,
     max_flow: i32,
-    finished: bool,
+    status: Result<(), MaxFlowError>,
     level: Vec<usize>,
     parents: Vec<NodeID>,
     stack: Vec<(NodeID, i32)>,
@@ -40,20 +40,29 @@ pub struct Dinic {
 }
 
 impl Dinic {
-    // todo(dl): add closure parameter to derive edge data
     pub fn from_generic_edge_list(
         input_edges: &[impl Edge<ID = NodeID>],
         source: NodeID,
         target: NodeID,
+    ) -> Self {
+        Self::from_generic_edge_list_with_capacity(input_edges, source, target, |_| 1)
+    }
+
+    /// Instantiates the solver with capacities that are derived from the edges
+    pub fn from_generic_edge_list_with_capacity<E: Edge<ID = NodeID>>(
+        input_edges: &[E],
+        source: NodeID,
+        target: NodeID,
+        capacity: impl Fn(&E) -> i32,
     ) -> Self {
         debug_assert!(!input_edges.is_empty());
         debug!("instantiating max-flow solver");
         let edge_list: Vec<InputEdge<ResidualCapacity>> = input_edges
             .iter()
-            .map(move |edge| InputEdge {
+            .map(|edge| InputEdge {
                 source: edge.source(),
                 target: edge.target(),
-                data: ResidualCapacity::new(1),
+                data: ResidualCapacity::new(capacity(edge)),
             })
             .collect();
 
@@ -66,7 +75,6 @@ impl Dinic {
         source: usize,
         target: usize,
     ) -> Self {
-        debug_assert!(!edge_list.is_empty());
         let number_of_edges = edge_list.len();
 
         debug!("extending {} edges", edge_list.len());
@@ -108,7 +116,7 @@ impl Dinic {
         Self {
             residual_graph: StaticGraph::new_from_sorted_list(edge_list),
             max_flow: 0,
-            finished: false,
+            status: Err(MaxFlowError::NotRun),
             level: Vec::new(),
             parents: Vec::new(),
             stack: Vec::new(),
@@ -255,6 +263,10 @@ impl MaxFlow for Dinic {
     }
 
     fn run(&mut self) {
+        self.status = check_instance(&self.residual_graph, self.source, self.target);
+        if self.status.is_err() {
+            return;
+        }
         debug!(
             "residual graph size: V {}, E {}",
             self.residual_graph.number_of_nodes(),
@@ -271,9 +283,11 @@ impl MaxFlow for Dinic {
             flow += self.dfs();
             if let Some(bound) = &self.bound {
                 // break early if an upper bound is known to the computation
-                if flow > bound.load(Ordering::Relaxed) {
+                let bound = bound.load(Ordering::Relaxed);
+                if flow > bound {
                     debug!("aborting max flow computation at {flow}");
                     self.max_flow = flow;
+                    self.status = Err(MaxFlowError::AbortedByUpperBound(bound));
                     return;
                 }
             }
@@ -282,12 +296,10 @@ impl MaxFlow for Dinic {
             bound.fetch_min(flow, Ordering::Relaxed);
         }
         self.max_flow = flow;
-        self.finished = true;
+        self.status = Ok(());
     }
-    fn max_flow(&self) -> Result<i32, String> {
-        if !self.finished {
-            return Err("Assigment was not computed.".to_string());
-        }
+    fn max_flow(&self) -> Result<i32, MaxFlowError> {
+        self.status?;
         debug!(
             "finished in {} DFS, and {} BFS runs",
             self.dfs_count, self.bfs_count
@@ -295,9 +307,10 @@ impl MaxFlow for Dinic {
         Ok(self.max_flow)
     }
 
-    fn assignment(&self, source: NodeID) -> Result<BitVec, String> {
-        if !self.finished {
-            return Err("Assigment was not computed.".to_string());
+    fn assignment(&self, source: NodeID) -> Result<BitVec, MaxFlowError> {
+        self.status?;
+        if source >= self.residual_graph.number_of_nodes() {
+            return Err(MaxFlowError::InvalidSource(source));
         }
 
         // run a reachability analysis
@@ -318,6 +331,10 @@ impl MaxFlow for Dinic {
         }
         Ok(reachable)
     }
+
+    fn residual_graph(&self) -> &StaticGraph<ResidualCapacity> {
+        &self.residual_graph
+    }
 }
 
 #[cfg(test)]
@@ -326,9 +343,12 @@ mod tests {
     use crate::dinic::Dinic;
     use crate::edge::InputEdge;
     use crate::max_flow::MaxFlow;
+    use crate::max_flow::MaxFlowError;
     use crate::max_flow::ResidualCapacity;
     use bitvec::bits;
     use bitvec::prelude::Lsb0;
+    use std::sync::atomic::AtomicI32;
+    use std::sync::Arc;
 
     #[test]
     fn max_flow_clr() {
@@ -512,4 +532,97 @@ mod tests {
             .assignment(1)
             .expect("assignment computation did not run");
     }
+
+    #[test]
+    fn error_not_run() {
+        let edges = vec![InputEdge::new(0, 1, ResidualCapacity::new(7))];
+        let max_flow_solver = Dinic::from_edge_list(edges, 0, 1);
+        assert_eq!(max_flow_solver.max_flow(), Err(MaxFlowError::NotRun));
+        assert_eq!(max_flow_solver.assignment(0), Err(MaxFlowError::NotRun));
+    }
+
+    #[test]
+    fn error_empty_graph() {
+        let mut max_flow_solver = Dinic::from_edge_list(Vec::new(), 0, 1);
+        max_flow_solver.run();
+        assert_eq!(max_flow_solver.max_flow(), Err(MaxFlowError::EmptyGraph));
+    }
+
+    #[test]
+    fn error_invalid_source_and_target() {
+        let edges = vec![
+            InputEdge::new(0, 1, ResidualCapacity::new(7)),
+            InputEdge::new(1, 2, ResidualCapacity::new(3)),
+        ];
+        let mut max_flow_solver = Dinic::from_edge_list(edges.clone(), 5, 2);
+        max_flow_solver.run();
+        assert_eq!(
+            max_flow_solver.max_flow(),
+            Err(MaxFlowError::InvalidSource(5))
+        );
+
+        let mut max_flow_solver = Dinic::from_edge_list(edges.clone(), 0, 5);
+        max_flow_solver.run();
+        assert_eq!(
+            max_flow_solver.max_flow(),
+            Err(MaxFlowError::InvalidTarget(5))
+        );
+
+        let mut max_flow_solver = Dinic::from_edge_list(edges, 1, 1);
+        max_flow_solver.run();
+        assert_eq!(
+            max_flow_solver.max_flow(),
+            Err(MaxFlowError::InvalidTarget(1))
+        );
+
+        let edges = vec![InputEdge::new(0, 1, ResidualCapacity::new(7))];
+        let mut max_flow_solver = Dinic::from_edge_list(edges, 0, 1);
+        max_flow_solver.run();
+        assert_eq!(max_flow_solver.max_flow(), Ok(7));
+        assert_eq!(
+            max_flow_solver.assignment(3),
+            Err(MaxFlowError::InvalidSource(3))
+        );
+    }
+
+    #[test]
+    fn error_aborted_by_upper_bound() {
+        let edges = vec![
+            InputEdge::new(0, 1, ResidualCapacity::new(7)),
+            InputEdge::new(0, 2, ResidualCapacity::new(3)),
+            InputEdge::new(1, 3, ResidualCapacity::new(6)),
+            InputEdge::new(2, 3, ResidualCapacity::new(8)),
+        ];
+        let mut max_flow_solver = Dinic::from_edge_list(edges, 0, 3);
+        max_flow_solver.run_with_upper_bound(Arc::new(AtomicI32::new(5)));
+        assert_eq!(
+            max_flow_solver.max_flow(),
+            Err(MaxFlowError::AbortedByUpperBound(5))
+        );
+    }
+
+    #[test]
+    fn max_flow_clr_with_capacity_closure() {
+        let edges = vec![
+            InputEdge::new(0, 1, 16),
+            InputEdge::new(0, 2, 13),
+            InputEdge::new(1, 2, 10),
+            InputEdge::new(1, 3, 12),
+            InputEdge::new(2, 1, 4),
+            InputEdge::new(2, 4, 14),
+            InputEdge::new(3, 2, 9),
+            InputEdge::new(3, 5, 20),
+            InputEdge::new(4, 3, 7),
+            InputEdge::new(4, 5, 4),
+        ];
+        let mut max_flow_solver =
+            Dinic::from_generic_edge_list_with_capacity(&edges, 0, 5, |edge| edge.data);
+        max_flow_solver.run();
+        assert_eq!(Ok(23), max_flow_solver.max_flow());
+
+        // unit capacities
+        let mut max_flow_solver = Dinic::from_generic_edge_list(&edges, 0, 5);
+        max_flow_solver.run();
+        assert_eq!(Ok(2), max_flow_solver.max_flow());
+    }
 }
@@ -0,0 +1,193 @@
+//! Computation of edge- and node-disjoint paths between two nodes by Menger's
+//! theorem. The graph is translated into a unit-capacity flow network, the
+//! max-flow is computed with Dinic's algorithm and the flow is decomposed into
+//! the actual paths.
+use core::cmp::max;
+
+use crate::{
+    dinic::Dinic,
+    edge::InputEdge,
+    flow_decomposition::decompose,
+    graph::{Graph, NodeID},
+    max_flow::{MaxFlow, ResidualCapacity},
+};
+
+/// Returns a maximum set of pairwise edge-disjoint paths from source to target
+pub fn edge_disjoint_paths<T>(
+    graph: &impl Graph<T>,
+    source: NodeID,
+    target: NodeID,
+) -> Vec<Vec<NodeID>> {
+    let mut edges = Vec::new();
+    for node in graph.node_range() {
+        for edge in graph.edge_range(node) {
+            let head = graph.target(edge);
+            // eigenloops are never part of a path
+            if node != head {
+                edges.push(InputEdge::new(node, head, ResidualCapacity::new(1)));
+            }
+        }
+    }
+
+    disjoint_flow_paths(edges, source, target)
+}
+
+/// Returns a maximum set of paths from source to target that are pairwise
+/// disjoint except for source and target themselves. Each node v is split into
+/// an entry node 2v and an exit node 2v+1 connected by a unit-capacity arc.
+pub fn node_disjoint_paths<T>(
+    graph: &impl Graph<T>,
+    source: NodeID,
+    target: NodeID,
+) -> Vec<Vec<NodeID>> {
+    // the split source would otherwise reach itself along cycles
+    if source == target {
+        return Vec::new();
+    }
+
+    let mut edges = Vec::new();
+    for node in graph.node_range() {
+        if node != source && node != target {
+            edges.push(InputEdge::new(
+                2 * node,
+                2 * node + 1,
+                ResidualCapacity::new(1),
+            ));
+        }
+        for edge in graph.edge_range(node) {
+            let head = graph.target(edge);
+            if node != head {
+                edges.push(InputEdge::new(
+                    2 * node + 1,
+                    2 * head,
+                    ResidualCapacity::new(1),
+                ));
+            }
+        }
+    }
+
+    // paths leave the source through its exit node and reach the target's entry
+    disjoint_flow_paths(edges, 2 * source + 1, 2 * target)
+        .into_iter()
+        .map(|path| {
+            let mut path = path.into_iter().map(|node| node / 2).collect::<Vec<_>>();
+            path.dedup();
+            path
+        })
+        .collect()
+}
+
+fn disjoint_flow_paths(
+    edges: Vec<InputEdge<ResidualCapacity>>,
+    source: NodeID,
+    target: NodeID,
+) -> Vec<Vec<NodeID>> {
+    let max_node_id = edges
+        .iter()
+        .fold(0, |acc, edge| max(acc, max(edge.source, edge.target)));
+    if source == target || edges.is_empty() || max(source, target) > max_node_id {
+        // no path can exist if source or target are not part of the network
+        return Vec::new();
+    }
+
+    let mut max_flow_solver = Dinic::from_edge_list(edges.clone(), source, target);
+    max_flow_solver.run();
+    let decomposition = decompose(&max_flow_solver, &edges, source, target)
+        .expect("max flow computation did not run");
+
+    // parallel edges may let a path carry more than one unit of flow
+    decomposition
+        .paths
+        .into_iter()
+        .flat_map(|(path, amount)| std::iter::repeat_n(path, amount as usize))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use crate::{
+        disjoint_paths::{edge_disjoint_paths, node_disjoint_paths},
+        edge::InputEdge,
+        graph::Graph,
+        static_graph::StaticGraph,
+    };
+
+    // two diamonds that share the node 3
+    fn bowtie() -> StaticGraph<i32> {
+        let edges = [
+            (0, 1),
+            (0, 2),
+            (1, 3),
+            (2, 3),
+            (3, 4),
+            (3, 5),
+            (4, 6),
+            (5, 6),
+        ]
+        .iter()
+        .flat_map(|(u, v)| [InputEdge::new(*u, *v, 1), InputEdge::new(*v, *u, 1)])
+        .collect();
+        StaticGraph::new(edges)
+    }
+
+    fn check_paths(graph: &StaticGraph<i32>, paths: &[Vec<usize>], source: usize, target: usize) {
+        for path in paths {
+            assert_eq!(path.first(), Some(&source));
+            assert_eq!(path.last(), Some(&target));
+            for pair in path.windows(2) {
+                assert!(graph.find_edge(pair[0], pair[1]).is_some());
+            }
+        }
+    }
+
+    #[test]
+    fn edge_disjoint() {
+        let graph = bowtie();
+        let paths = edge_disjoint_paths(&graph, 0, 6);
+        assert_eq!(paths.len(), 2);
+        check_paths(&graph, &paths, 0, 6);
+
+        let mut used_edges = HashSet::new();
+        for path in &paths {
+            for pair in path.windows(2) {
+                assert!(used_edges.insert((pair[0], pair[1])));
+            }
+        }
+    }
+
+    #[test]
+    fn node_disjoint() {
+        let graph = bowtie();
+        let paths = node_disjoint_paths(&graph, 0, 6);
+        assert_eq!(paths.len(), 1);
+        check_paths(&graph, &paths, 0, 6);
+
+        let paths = node_disjoint_paths(&graph, 0, 3);
+        assert_eq!(paths.len(), 2);
+        check_paths(&graph, &paths, 0, 3);
+        let mut used_nodes = HashSet::new();
+        for path in &paths {
+            for node in &path[1..path.len() - 1] {
+                assert!(used_nodes.insert(*node));
+            }
+        }
+    }
+
+    #[test]
+    fn unreachable_target() {
+        let graph = StaticGraph::new(vec![InputEdge::new(0, 1, 1), InputEdge::new(2, 3, 1)]);
+        assert!(edge_disjoint_paths(&graph, 0, 3).is_empty());
+        assert!(node_disjoint_paths(&graph, 0, 3).is_empty());
+        assert!(edge_disjoint_paths(&graph, 0, 0).is_empty());
+    }
+
+    #[test]
+    fn same_source_and_target() {
+        // the cycles through node 0 are not paths
+        let graph = bowtie();
+        assert!(edge_disjoint_paths(&graph, 0, 0).is_empty());
+        assert!(node_disjoint_paths(&graph, 0, 0).is_empty());
+    }
+}
@@ -14,6 +14,11 @@ pub trait EdgeData {
     fn data(&self) -> &Self::DATA;
 }
 
+/// The capacity of an edge in cut and flow computations
+pub trait EdgeCapacity {
+    fn capacity(&self) -> i32;
+}
+
 #[derive(Clone, Copy)]
 pub struct TrivialEdge {
     pub source: usize,
@@ -30,6 +35,12 @@ impl Edge for TrivialEdge {
     }
 }
 
+impl EdgeCapacity for TrivialEdge {
+    fn capacity(&self) -> i32 {
+        1
+    }
+}
+
 #[derive(Clone, Copy, Debug, Default, Deserialize, Eq, PartialOrd, Ord, PartialEq, Serialize)]
 pub struct InputEdge<EdgeDataT: Eq> {
     pub source: NodeID,
@@ -54,6 +65,12 @@ impl<EdgeDataT: std::cmp::Eq> EdgeData for InputEdge<EdgeDataT> {
     }
 }
 
+impl EdgeCapacity for InputEdge<i32> {
+    fn capacity(&self) -> i32 {
+        self.data
+    }
+}
+
 impl<EdgeDataT: Eq> InputEdge<EdgeDataT> {
     pub fn new(source: NodeID, target: NodeID, data: EdgeDataT) -> Self {
         Self {
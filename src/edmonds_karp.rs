@@ -2,7 +2,7 @@ use crate::{
     dfs::DFS,
     edge::{Edge, InputEdge},
     graph::{Graph, NodeID},
-    max_flow::{MaxFlow, ResidualCapacity},
+    max_flow::{check_instance, MaxFlow, MaxFlowError, ResidualCapacity},
     static_graph::StaticGraph,
 };
 use bitvec::vec::BitVec;
@@ -19,7 +19,7 @@ use std::{
 pub struct EdmondsKarp {
     residual_graph: StaticGraph<ResidualCapacity>,
     max_flow: i32,
-    finished: bool,
+    status: Result<(), MaxFlowError>,
     source: NodeID,
     target: NodeID,
     bound: Option<Arc<AtomicI32>>,
@@ -84,7 +84,7 @@ impl EdmondsKarp {
         Self {
             residual_graph: StaticGraph::new(edge_list),
             max_flow: 0,
-            finished: false,
+            status: Err(MaxFlowError::NotRun),
             source,
             target,
             bound: None,
@@ -100,6 +100,10 @@ impl MaxFlow for EdmondsKarp {
     }
 
     fn run(&mut self) {
+        self.status = check_instance(&self.residual_graph, self.source, self.target);
+        if self.status.is_err() {
+            return;
+        }
         let mut dfs = DFS::new(
             &[self.source],
             &[self.target],
@@ -146,19 +150,18 @@ impl MaxFlow for EdmondsKarp {
             debug!(" flow assignment3 took: {:?} (done)", duration);
         }
 
-        self.finished = true;
+        self.status = Ok(());
     }
 
-    fn max_flow(&self) -> Result<i32, String> {
-        if !self.finished {
-            return Err("Assigment was not computed.".to_string());
-        }
+    fn max_flow(&self) -> Result<i32, MaxFlowError> {
+        self.status?;
         Ok(self.max_flow)
     }
 
-    fn assignment(&self, source: NodeID) -> Result<BitVec, String> {
-        if !self.finished {
-            return Err("Assigment was not computed.".to_string());
+    fn assignment(&self, source: NodeID) -> Result<BitVec, MaxFlowError> {
+        self.status?;
+        if source >= self.residual_graph.number_of_nodes() {
+            return Err(MaxFlowError::InvalidSource(source));
         }
 
         // run a reachability analysis
@@ -179,14 +182,21 @@ impl MaxFlow for EdmondsKarp {
         }
         Ok(reachable)
     }
+
+    fn residual_graph(&self) -> &StaticGraph<ResidualCapacity> {
+        &self.residual_graph
+    }
 }
 
 #[cfg(test)]
 mod tests {
 
+    use std::sync::{atomic::AtomicI32, Arc};
+
     use crate::edge::InputEdge;
     use crate::edmonds_karp::EdmondsKarp;
     use crate::max_flow::MaxFlow;
+    use crate::max_flow::MaxFlowError;
     use crate::max_flow::ResidualCapacity;
     use bitvec::bits;
     use bitvec::prelude::Lsb0;
@@ -373,4 +383,59 @@ mod tests {
             .assignment(0)
             .expect("assignment computation did not run");
     }
+
+    #[test]
+    fn error_not_run() {
+        let edges = vec![InputEdge::new(0, 1, ResidualCapacity::new(7))];
+        let max_flow_solver = EdmondsKarp::from_edge_list(edges, 0, 1);
+        assert_eq!(max_flow_solver.max_flow(), Err(MaxFlowError::NotRun));
+        assert_eq!(max_flow_solver.assignment(0), Err(MaxFlowError::NotRun));
+    }
+
+    #[test]
+    fn error_empty_graph() {
+        let mut max_flow_solver = EdmondsKarp::from_edge_list(Vec::new(), 0, 1);
+        max_flow_solver.run();
+        assert_eq!(max_flow_solver.max_flow(), Err(MaxFlowError::EmptyGraph));
+    }
+
+    #[test]
+    fn error_invalid_source_and_target() {
+        let edges = vec![
+            InputEdge::new(0, 1, ResidualCapacity::new(7)),
+            InputEdge::new(1, 2, ResidualCapacity::new(3)),
+        ];
+        for (source, target, error) in [
+            (5, 2, MaxFlowError::InvalidSource(5)),
+            (0, 5, MaxFlowError::InvalidTarget(5)),
+            (1, 1, MaxFlowError::InvalidTarget(1)),
+        ] {
+            let mut max_flow_solver = EdmondsKarp::from_edge_list(edges.clone(), source, target);
+            max_flow_solver.run();
+            assert_eq!(max_flow_solver.max_flow(), Err(error));
+            assert_eq!(max_flow_solver.assignment(source), Err(error));
+        }
+
+        let mut max_flow_solver = EdmondsKarp::from_edge_list(edges, 0, 2);
+        max_flow_solver.run();
+        assert_eq!(max_flow_solver.max_flow(), Ok(3));
+        assert_eq!(
+            max_flow_solver.assignment(3),
+            Err(MaxFlowError::InvalidSource(3))
+        );
+    }
+
+    #[test]
+    fn upper_bound_is_discarded() {
+        // unlike Dinic, the solver does not abort once the flow exceeds the bound
+        let edges = vec![
+            InputEdge::new(0, 1, ResidualCapacity::new(7)),
+            InputEdge::new(0, 2, ResidualCapacity::new(3)),
+            InputEdge::new(1, 3, ResidualCapacity::new(6)),
+            InputEdge::new(2, 3, ResidualCapacity::new(8)),
+        ];
+        let mut max_flow_solver = EdmondsKarp::from_edge_list(edges, 0, 3);
+        max_flow_solver.run_with_upper_bound(Arc::new(AtomicI32::new(5)));
+        assert_eq!(max_flow_solver.max_flow(), Ok(9));
+    }
 }
@@ -0,0 +1,563 @@
+//! Implementation of the FlowCutter approach to graph bisection by Hamann and
+//! Strasser. Starting from a single source and target node, the source and
+//! target sides are grown alternately. The smaller side is extended by all the
+//! nodes it reaches in the residual graph and by a pierced node on the other
+//! side of the cut. The flow is augmented incrementally, and only piercing a
+//! node that is reached from the other side increases it. The most balanced
+//! cuts of each flow value form a Pareto front of cut size and balance.
+use std::collections::{BinaryHeap, VecDeque};
+
+use bitvec::vec::BitVec;
+use core::cmp::max;
+use itertools::Itertools;
+use log::debug;
+
+use crate::{
+    edge::TrivialEdge,
+    geometry::primitives::FPCoordinate,
+    inertial_flow::{flow_cmp, Axis, FlowResult},
+    renumbering_table::RenumberingTable,
+};
+
+const UNREACHED: usize = usize::MAX;
+
+/// Computes the FlowCutter Pareto front for an axis, where source and target
+/// are the extreme nodes of the ordering along the axis.
+///
+/// # Arguments
+///
+/// * `input_edges` - a list of edges that represents the input graph
+/// * `node_id_list` - list of node ids
+/// * `coordinates` - immutable slice of coordinates of the graphs nodes
+/// * `axis` - the axis to pick source and target from
+pub fn sub_step(
+    input_edges: &[TrivialEdge],
+    node_id_list: &[usize],
+    coordinates: &[FPCoordinate],
+    axis: &Axis,
+) -> Vec<FlowResult> {
+    debug_assert!(node_id_list.len() > 1);
+    let (source, target) = match node_id_list.iter().position_minmax_by(|a, b| {
+        axis.project(&coordinates[**a])
+            .total_cmp(&axis.project(&coordinates[**b]))
+    }) {
+        itertools::MinMaxResult::MinMax(min, max) if min != max => (min, max),
+        _ => (0, node_id_list.len() - 1),
+    };
+
+    pareto_front(
+        input_edges,
+        node_id_list,
+        node_id_list[source],
+        node_id_list[target],
+    )
+}
+
+/// Computes a Pareto front of cuts that separate source and target. The cuts
+/// are ordered by increasing flow and each cut is strictly better balanced than
+/// its predecessors.
+pub fn pareto_front(
+    input_edges: &[TrivialEdge],
+    node_id_list: &[usize],
+    source: usize,
+    target: usize,
+) -> Vec<FlowResult> {
+    debug_assert!(source != target);
+    let number_of_nodes = node_id_list.len();
+    let universe_size = input_edges
+        .iter()
+        .fold(max(source, target) + 1, |acc, edge| {
+            max(acc, max(edge.source, edge.target) + 1)
+        });
+    let universe_size = node_id_list
+        .iter()
+        .fold(universe_size, |acc, id| max(acc, id + 1));
+
+    // nodes are numbered consecutively in the order of the node id list
+    let mut renumbering_table =
+        RenumberingTable::new_with_size_hint(universe_size, number_of_nodes);
+    for (local_id, id) in node_id_list.iter().enumerate() {
+        renumbering_table.set(*id, local_id);
+    }
+    let edges = input_edges
+        .iter()
+        .filter(|edge| edge.source != edge.target)
+        .map(|edge| {
+            (
+                renumbering_table.get(edge.source),
+                renumbering_table.get(edge.target),
+            )
+        })
+        .collect_vec();
+    let source = renumbering_table.get(source);
+    let target = renumbering_table.get(target);
+
+    let mut cutter = Cutter::new(number_of_nodes, &edges, source, target);
+    let front = cutter.run(node_id_list);
+    debug!("pareto front has {} cuts", front.len());
+    front
+}
+
+/// Returns the best cut of a collection of (Pareto fronts of) cuts, i.e. the
+/// cut of minimum flow among those with at least the given balance. If no cut
+/// satisfies the balance constraint, the most balanced cut is returned.
+pub fn best_cut(
+    cuts: impl IntoIterator<Item = FlowResult>,
+    minimum_balance: f64,
+) -> Option<FlowResult> {
+    let (feasible, infeasible): (Vec<_>, Vec<_>) = cuts
+        .into_iter()
+        .partition(|cut| cut.balance >= minimum_balance);
+    if !feasible.is_empty() {
+        return feasible.into_iter().min_by(flow_cmp);
+    }
+    infeasible
+        .into_iter()
+        .max_by(|a, b| a.balance.partial_cmp(&b.balance).unwrap())
+}
+
+/// Returns the best cut for an uneven split, where the left side targets the
+/// given ratio of the nodes. The balance constraint is scaled by the share of
+/// the smaller side, and the sides of the cut are swapped if necessary.
+pub fn best_cut_with_ratio(
+    cuts: impl IntoIterator<Item = FlowResult>,
+    minimum_balance: f64,
+    ratio: f64,
+) -> Option<FlowResult> {
+    let smaller_share = ratio.min(1. - ratio);
+    let mut cut = best_cut(cuts, minimum_balance * 2. * smaller_share)?;
+    if ratio != 0.5 && (cut.left_ids.len() < cut.right_ids.len()) != (ratio < 0.5) {
+        std::mem::swap(&mut cut.left_ids, &mut cut.right_ids);
+    }
+    Some(cut)
+}
+
+fn side_balance(side_size: usize, number_of_nodes: usize) -> f64 {
+    std::cmp::min(side_size, number_of_nodes - side_size) as f64 / number_of_nodes as f64
+}
+
+fn bfs_distances(number_of_nodes: usize, edges: &[(usize, usize)], start: usize) -> Vec<usize> {
+    let mut adjacency = vec![Vec::new(); number_of_nodes];
+    for (u, v) in edges {
+        adjacency[*u].push(*v);
+        adjacency[*v].push(*u);
+    }
+    let mut distances = vec![usize::MAX; number_of_nodes];
+    let mut queue = VecDeque::new();
+    distances[start] = 0;
+    queue.push_back(start);
+    while let Some(u) = queue.pop_front() {
+        for v in &adjacency[u] {
+            if distances[*v] == usize::MAX {
+                distances[*v] = distances[u] + 1;
+                queue.push_back(*v);
+            }
+        }
+    }
+    distances
+}
+
+/// One of the two sides of the cutter. The reach of a side are the nodes that
+/// are reachable from it in the residual graph, or that reach it in case of
+/// the target side. Nodes remember the step in which they joined the reach, so
+/// that the cuts of earlier steps of the same flow value can be restored.
+struct Side {
+    assigned: BitVec,
+    reach_step: Vec<usize>,
+    reach_order: Vec<usize>,
+    assigned_prefix: usize,
+    // candidates for piercing, ordered by preference
+    candidates: BinaryHeap<(i64, usize)>,
+    // candidates that would increase the flow, deferred until it changes
+    augmenting_candidates: BinaryHeap<(i64, usize)>,
+    // distance from the own and the other terminal
+    rank: Vec<i64>,
+}
+
+impl Side {
+    fn new(number_of_nodes: usize, terminal: usize, rank: Vec<i64>) -> Self {
+        let mut assigned = BitVec::repeat(false, number_of_nodes);
+        assigned.set(terminal, true);
+        Self {
+            assigned,
+            reach_step: vec![UNREACHED; number_of_nodes],
+            reach_order: Vec::new(),
+            assigned_prefix: 0,
+            candidates: BinaryHeap::new(),
+            augmenting_candidates: BinaryHeap::new(),
+            rank,
+        }
+    }
+
+    fn reaches(&self, node: usize) -> bool {
+        self.reach_step[node] != UNREACHED
+    }
+
+    /// Assigns all nodes of the reach to the side
+    fn assign_reach(&mut self) {
+        for node in &self.reach_order[self.assigned_prefix..] {
+            self.assigned.set(*node, true);
+        }
+        self.assigned_prefix = self.reach_order.len();
+    }
+}
+
+/// Unit capacity residual network with source and target sides. Each input
+/// edge is represented by a forward arc and a reverse arc of capacity zero.
+struct Cutter {
+    first_arc: Vec<usize>,
+    heads: Vec<usize>,
+    twins: Vec<usize>,
+    residual: Vec<i32>,
+    sides: [Side; 2],
+}
+
+const SOURCE: usize = 0;
+const TARGET: usize = 1;
+
+impl Cutter {
+    fn new(number_of_nodes: usize, edges: &[(usize, usize)], source: usize, target: usize) -> Self {
+        // arc 2i is the forward and arc 2i+1 the reverse arc of edge i
+        let arcs = edges
+            .iter()
+            .flat_map(|(u, v)| [(*u, *v, 1), (*v, *u, 0)])
+            .collect_vec();
+        let mut order = (0..arcs.len()).collect_vec();
+        order.sort_by_key(|arc| arcs[*arc].0);
+        let mut position = vec![0; arcs.len()];
+        for (new_index, arc) in order.iter().enumerate() {
+            position[*arc] = new_index;
+        }
+
+        let mut first_arc = vec![0; number_of_nodes + 1];
+        for (tail, _, _) in &arcs {
+            first_arc[tail + 1] += 1;
+        }
+        for node in 0..number_of_nodes {
+            first_arc[node + 1] += first_arc[node];
+        }
+        let heads = order.iter().map(|arc| arcs[*arc].1).collect_vec();
+        let residual = order.iter().map(|arc| arcs[*arc].2).collect_vec();
+        let twins = order.iter().map(|arc| position[*arc ^ 1]).collect_vec();
+
+        // prefer piercing nodes close to the growing side and far from the other
+        let source_distances = bfs_distances(number_of_nodes, edges, source);
+        let target_distances = bfs_distances(number_of_nodes, edges, target);
+        let distance = |distances: &[usize], node: usize| {
+            if distances[node] == usize::MAX {
+                number_of_nodes as i64
+            } else {
+                distances[node] as i64
+            }
+        };
+        let source_rank = (0..number_of_nodes)
+            .map(|node| distance(&target_distances, node) - distance(&source_distances, node))
+            .collect_vec();
+        let target_rank = source_rank.iter().map(|rank| -rank).collect_vec();
+
+        Self {
+            first_arc,
+            heads,
+            twins,
+            residual,
+            sides: [
+                Side::new(number_of_nodes, source, source_rank),
+                Side::new(number_of_nodes, target, target_rank),
+            ],
+        }
+    }
+
+    fn number_of_nodes(&self) -> usize {
+        self.first_arc.len() - 1
+    }
+
+    /// Computes the Pareto front by growing source and target sides
+    fn run(&mut self, node_id_list: &[usize]) -> Vec<FlowResult> {
+        let number_of_nodes = self.number_of_nodes();
+        let maximum_balance = (number_of_nodes / 2) as f64 / number_of_nodes as f64;
+
+        let mut flow = 0;
+        while self.augment() {
+            flow += 1;
+        }
+        let mut step = 0;
+        self.reset_reach(step);
+
+        let mut front: Vec<FlowResult> = Vec::new();
+        // balance, step and side of the best cut of the current flow value
+        let mut level_best: Option<(f64, usize, usize)> = None;
+        loop {
+            let source_count = self.sides[SOURCE].reach_order.len();
+            let target_count = self.sides[TARGET].reach_order.len();
+            let source_cut_balance = side_balance(source_count, number_of_nodes);
+            let target_cut_balance = side_balance(target_count, number_of_nodes);
+            let (balance, side) = if source_cut_balance >= target_cut_balance {
+                (source_cut_balance, SOURCE)
+            } else {
+                (target_cut_balance, TARGET)
+            };
+            if level_best.is_none_or(|(best, _, _)| best < balance) {
+                level_best = Some((balance, step, side));
+            }
+            if balance >= maximum_balance {
+                // perfectly balanced, no further improvement possible
+                break;
+            }
+
+            // grow the smaller side by all nodes that it reaches and pierce the cut
+            step += 1;
+            let growing = if source_count <= target_count {
+                SOURCE
+            } else {
+                TARGET
+            };
+            let Some(node) = self.pierce(growing) else {
+                break;
+            };
+            let augmenting = self.sides[1 - growing].reaches(node);
+            self.sides[growing].assign_reach();
+            self.sides[growing].assigned.set(node, true);
+
+            if augmenting {
+                debug!("flow: {flow}, source side: {source_count}, target side: {target_count}");
+                self.add_to_front(&mut front, level_best.take(), flow, node_id_list);
+                while self.augment() {
+                    flow += 1;
+                }
+                self.reset_reach(step);
+            } else {
+                self.extend_reach(growing, node, step);
+            }
+        }
+        self.add_to_front(&mut front, level_best, flow, node_id_list);
+        front
+    }
+
+    /// Restores the cut of the given step and side, and adds it to the front if
+    /// it is better balanced than all previous cuts.
+    fn add_to_front(
+        &self,
+        front: &mut Vec<FlowResult>,
+        level_best: Option<(f64, usize, usize)>,
+        flow: i32,
+        node_id_list: &[usize],
+    ) {
+        let Some((balance, step, side)) = level_best else {
+            return;
+        };
+        if front.last().is_some_and(|last| last.balance >= balance) {
+            return;
+        }
+        let reach_step = &self.sides[side].reach_step;
+        let (left_ids, right_ids): (Vec<_>, Vec<_>) = node_id_list
+            .iter()
+            .enumerate()
+            .partition_map(|(local_id, id)| {
+                // the source side of the cut is on the left
+                if (reach_step[local_id] <= step) == (side == SOURCE) {
+                    itertools::Either::Left(*id)
+                } else {
+                    itertools::Either::Right(*id)
+                }
+            });
+        front.push(FlowResult {
+            flow,
+            balance,
+            left_ids,
+            right_ids,
+        });
+    }
+
+    /// Finds a single augmenting path from the source side to the target side
+    /// and augments the flow along it.
+    fn augment(&mut self) -> bool {
+        let mut parent_arc = vec![UNREACHED; self.number_of_nodes()];
+        let mut queue: VecDeque<usize> = self.sides[SOURCE].assigned.iter_ones().collect();
+        for node in &queue {
+            // mark the source side as visited
+            parent_arc[*node] = self.heads.len();
+        }
+        while let Some(u) = queue.pop_front() {
+            for arc in self.first_arc[u]..self.first_arc[u + 1] {
+                let v = self.heads[arc];
+                if self.residual[arc] == 0 || parent_arc[v] != UNREACHED {
+                    continue;
+                }
+                parent_arc[v] = arc;
+                if self.sides[TARGET].assigned[v] {
+                    // walk back to the source side
+                    let mut node = v;
+                    while parent_arc[node] != self.heads.len() {
+                        let arc = parent_arc[node];
+                        self.residual[arc] -= 1;
+                        self.residual[self.twins[arc]] += 1;
+                        node = self.heads[self.twins[arc]];
+                    }
+                    return true;
+                }
+                queue.push_back(v);
+            }
+        }
+        false
+    }
+
+    /// Recomputes the reach of both sides after the flow changed
+    fn reset_reach(&mut self, step: usize) {
+        for side in &mut self.sides {
+            side.reach_step.fill(UNREACHED);
+            side.reach_order.clear();
+            side.assigned_prefix = 0;
+            let deferred = std::mem::take(&mut side.augmenting_candidates);
+            side.candidates.extend(deferred);
+        }
+        for side in [SOURCE, TARGET] {
+            let assigned = self.sides[side].assigned.iter_ones().collect_vec();
+            for node in assigned {
+                self.extend_reach(side, node, step);
+            }
+        }
+    }
+
+    /// Extends the reach of a side by the given node and all nodes reachable
+    /// from it, or reaching it in case of the target side.
+    fn extend_reach(&mut self, side: usize, start: usize, step: usize) {
+        if self.sides[side].reaches(start) {
+            return;
+        }
+        let mut stack = vec![start];
+        self.mark_reached(side, start, step);
+        while let Some(u) = stack.pop() {
+            for arc in self.first_arc[u]..self.first_arc[u + 1] {
+                let v = self.heads[arc];
+                // the target side follows arcs in reverse direction
+                let capacity = if side == SOURCE {
+                    self.residual[arc]
+                } else {
+                    self.residual[self.twins[arc]]
+                };
+                if capacity > 0 && !self.sides[side].reaches(v) {
+                    self.mark_reached(side, v, step);
+                    stack.push(v);
+                }
+            }
+        }
+    }
+
+    fn mark_reached(&mut self, side: usize, node: usize, step: usize) {
+        let side = &mut self.sides[side];
+        side.reach_step[node] = step;
+        side.reach_order.push(node);
+        for arc in self.first_arc[node]..self.first_arc[node + 1] {
+            let neighbor = self.heads[arc];
+            side.candidates.push((side.rank[neighbor], neighbor));
+        }
+    }
+
+    /// Selects a node next to the reach of the growing side. Nodes that are
+    /// not reached from the other side are preferred, since piercing them does
+    /// not increase the flow. Ties are broken by distances.
+    fn pierce(&mut self, growing: usize) -> Option<usize> {
+        let (first, second) = self.sides.split_at_mut(1);
+        let (side, other) = if growing == SOURCE {
+            (&mut first[0], &second[0])
+        } else {
+            (&mut second[0], &first[0])
+        };
+        while let Some((rank, node)) = side.candidates.pop() {
+            if side.reaches(node) || other.assigned[node] {
+                continue;
+            }
+            if other.reaches(node) {
+                side.augmenting_candidates.push((rank, node));
+                continue;
+            }
+            return Some(node);
+        }
+        while let Some((_, node)) = side.augmenting_candidates.pop() {
+            if !side.reaches(node) && !other.assigned[node] {
+                return Some(node);
+            }
+        }
+        // the growing side is disconnected from the remaining nodes
+        (0..side.assigned.len())
+            .filter(|node| !side.reaches(*node) && !other.assigned[*node])
+            .max_by_key(|node| (!other.reaches(*node), side.rank[*node]))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use itertools::Itertools;
+
+    use crate::{
+        edge::TrivialEdge,
+        flow_cutter::{best_cut, best_cut_with_ratio, pareto_front, sub_step},
+        geometry::primitives::FPCoordinate,
+        inertial_flow::Axes,
+        test_utils::{dumbbell, grid_coordinates},
+    };
+
+    // two 3x3 grids side by side that are connected by a single edge
+    fn barbell() -> (Vec<TrivialEdge>, Vec<FPCoordinate>) {
+        let coordinates = grid_coordinates(3, 3);
+        let shifted = coordinates
+            .iter()
+            .map(|coordinate| FPCoordinate::new(coordinate.lat, coordinate.lon + 4000))
+            .collect_vec();
+        (dumbbell(3), [coordinates, shifted].concat())
+    }
+
+    #[test]
+    fn barbell_front() {
+        let (edges, _) = barbell();
+        let node_id_list = (0..18).collect_vec();
+        let front = pareto_front(&edges, &node_id_list, 0, 17);
+
+        // flows are increasing while balances are strictly increasing
+        assert!(!front.is_empty());
+        assert!(front
+            .windows(2)
+            .all(|pair| pair[0].flow < pair[1].flow && pair[0].balance < pair[1].balance));
+
+        let last = front.last().unwrap();
+        assert_eq!(last.flow, 1);
+        assert_eq!(last.balance, 0.5);
+        assert_eq!(last.left_ids, (0..9).collect_vec());
+        assert_eq!(last.right_ids, (9..18).collect_vec());
+    }
+
+    #[test]
+    fn barbell_sub_step() {
+        let (edges, coordinates) = barbell();
+        let node_id_list = (0..18).collect_vec();
+        let fronts = Axes::new()
+            .as_slice()
+            .iter()
+            .flat_map(|axis| sub_step(&edges, &node_id_list, &coordinates, axis))
+            .collect_vec();
+
+        let best = best_cut(fronts, 0.25).unwrap();
+        assert_eq!(best.flow, 1);
+        assert_eq!(best.balance, 0.5);
+    }
+
+    #[test]
+    fn best_cut_fallback() {
+        let (edges, _) = barbell();
+        let node_id_list = (0..18).collect_vec();
+        let front = pareto_front(&edges, &node_id_list, 0, 17);
+        let best = best_cut(front, 0.75).unwrap();
+        assert_eq!(best.balance, 0.5);
+    }
+
+    #[test]
+    fn uneven_best_cut() {
+        let (edges, _) = barbell();
+        let node_id_list = (0..18).collect_vec();
+        let front = pareto_front(&edges, &node_id_list, 0, 17);
+        // a third of the nodes on the right
+        let best = best_cut_with_ratio(front, 0.25, 2. / 3.).unwrap();
+        assert!(best.left_ids.len() >= best.right_ids.len());
+        assert!(best.right_ids.len() >= 3);
+    }
+}
@@ -0,0 +1,263 @@
+//! Decomposition of a flow into s-t paths and circulations. Every feasible flow
+//! can be decomposed into at most |E| paths and cycles, each carrying a positive
+//! amount of flow. The decomposition works on the per-edge flows that are
+//! recovered from the residual graph of a max-flow solver.
+use std::collections::HashMap;
+
+use core::cmp::{max, min};
+
+use crate::{
+    edge::InputEdge,
+    graph::{Graph, NodeID},
+    max_flow::{MaxFlow, MaxFlowError, ResidualCapacity},
+};
+
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct FlowDecomposition {
+    /// s-t paths given as node sequences and the amount of flow they carry
+    pub paths: Vec<(Vec<NodeID>, i32)>,
+    /// circulations given as closed node sequences, i.e. first node equals last
+    pub cycles: Vec<(Vec<NodeID>, i32)>,
+}
+
+impl FlowDecomposition {
+    /// Returns the accumulated flow of all s-t paths
+    pub fn flow_value(&self) -> i32 {
+        self.paths.iter().map(|(_, amount)| amount).sum()
+    }
+}
+
+/// Recovers the flow on each of the input edges from the residual graph of a
+/// finished max-flow computation. Parallel input edges are merged by the solvers,
+/// and their net flow is distributed greedily in the order of the input.
+pub fn edge_flows(
+    solver: &impl MaxFlow,
+    input_edges: &[InputEdge<ResidualCapacity>],
+) -> Result<Vec<i32>, MaxFlowError> {
+    solver.max_flow()?;
+    let residual_graph = solver.residual_graph();
+
+    // accumulate capacities of parallel edges as they were merged by the solver
+    let mut capacities: HashMap<(NodeID, NodeID), i32> = HashMap::new();
+    for edge in input_edges {
+        if edge.source != edge.target {
+            *capacities.entry((edge.source, edge.target)).or_default() += edge.data.capacity;
+        }
+    }
+
+    // the net flow along (u,v) is the difference of original and residual capacity
+    let mut net_flows: HashMap<(NodeID, NodeID), i32> = capacities
+        .iter()
+        .map(|(&(u, v), &capacity)| {
+            let edge = residual_graph.find_edge_unchecked(u, v);
+            ((u, v), capacity - residual_graph.data(edge).capacity)
+        })
+        .collect();
+
+    let flows = input_edges
+        .iter()
+        .map(|edge| {
+            if edge.source == edge.target {
+                return 0;
+            }
+            let remaining = net_flows.get_mut(&(edge.source, edge.target)).unwrap();
+            let flow = min(max(*remaining, 0), edge.data.capacity);
+            *remaining -= flow;
+            flow
+        })
+        .collect();
+    Ok(flows)
+}
+
+/// Decomposes the flow of a finished max-flow computation into s-t paths and
+/// circulations.
+pub fn decompose(
+    solver: &impl MaxFlow,
+    input_edges: &[InputEdge<ResidualCapacity>],
+    source: NodeID,
+    target: NodeID,
+) -> Result<FlowDecomposition, MaxFlowError> {
+    let flows = edge_flows(solver, input_edges)?;
+    Ok(decompose_flows(input_edges, &flows, source, target))
+}
+
+/// Decomposes a flow given by the amount of flow on each of the input edges
+/// into s-t paths and circulations. The flow is assumed to be feasible.
+pub fn decompose_flows(
+    input_edges: &[InputEdge<ResidualCapacity>],
+    flows: &[i32],
+    source: NodeID,
+    target: NodeID,
+) -> FlowDecomposition {
+    debug_assert_eq!(input_edges.len(), flows.len());
+    let number_of_nodes = input_edges
+        .iter()
+        .fold(max(source, target) + 1, |acc, edge| {
+            max(acc, max(edge.source, edge.target) + 1)
+        });
+
+    // adjacency lists of edges that carry flow, and a pointer to the first
+    // edge of each list that was not exhausted yet.
+    let mut arcs: Vec<Vec<(NodeID, i32)>> = vec![Vec::new(); number_of_nodes];
+    for (edge, flow) in input_edges.iter().zip(flows) {
+        if *flow > 0 && edge.source != edge.target {
+            arcs[edge.source].push((edge.target, *flow));
+        }
+    }
+    let mut current_arc = vec![0; number_of_nodes];
+
+    let mut decomposition = FlowDecomposition::default();
+    let mut walk = Vec::new();
+    let mut position_on_walk: HashMap<NodeID, usize> = HashMap::new();
+
+    // first extract all s-t paths, then all remaining circulations
+    let starts = std::iter::once(source).chain(0..number_of_nodes);
+    for start in starts {
+        walk.clear();
+        position_on_walk.clear();
+        walk.push(start);
+        position_on_walk.insert(start, 0);
+
+        while let Some(&u) = walk.last() {
+            if u == target && start == source {
+                // found an s-t path
+                let amount = bottleneck(&walk, &arcs, &current_arc);
+                augment(&walk, amount, &mut arcs, &mut current_arc);
+                decomposition.paths.push((walk.clone(), amount));
+                walk.truncate(1);
+                position_on_walk.clear();
+                position_on_walk.insert(start, 0);
+                continue;
+            }
+
+            while current_arc[u] < arcs[u].len() && arcs[u][current_arc[u]].1 == 0 {
+                current_arc[u] += 1;
+            }
+            if current_arc[u] == arcs[u].len() {
+                // no flow leaves u. This only happens at the start of the walk
+                // for feasible flows.
+                debug_assert_eq!(walk.len(), 1, "flow is not conserved at {u}");
+                break;
+            }
+
+            let v = arcs[u][current_arc[u]].0;
+            if let Some(&index) = position_on_walk.get(&v) {
+                // closed a cycle, remove it from the walk
+                walk.push(v);
+                let cycle = walk.split_off(index);
+                let amount = bottleneck(&cycle, &arcs, &current_arc);
+                augment(&cycle, amount, &mut arcs, &mut current_arc);
+                for node in &cycle[1..cycle.len() - 1] {
+                    position_on_walk.remove(node);
+                }
+                walk.push(v);
+                decomposition.cycles.push((cycle, amount));
+            } else {
+                position_on_walk.insert(v, walk.len());
+                walk.push(v);
+            }
+        }
+    }
+
+    decomposition
+}
+
+fn bottleneck(walk: &[NodeID], arcs: &[Vec<(NodeID, i32)>], current_arc: &[usize]) -> i32 {
+    walk.windows(2)
+        .map(|pair| arcs[pair[0]][current_arc[pair[0]]].1)
+        .min()
+        .unwrap_or(0)
+}
+
+fn augment(
+    walk: &[NodeID],
+    amount: i32,
+    arcs: &mut [Vec<(NodeID, i32)>],
+    current_arc: &mut [usize],
+) {
+    for pair in walk.windows(2) {
+        let u = pair[0];
+        arcs[u][current_arc[u]].1 -= amount;
+        if arcs[u][current_arc[u]].1 == 0 {
+            current_arc[u] += 1;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        dinic::Dinic,
+        edge::InputEdge,
+        edmonds_karp::EdmondsKarp,
+        flow_decomposition::{decompose, decompose_flows, edge_flows},
+        flow_verification::verify,
+        ford_fulkerson::FordFulkerson,
+        max_flow::{MaxFlow, ResidualCapacity},
+        test_utils::clr_edges,
+    };
+
+    fn check_solver(solver: &impl MaxFlow) {
+        let edges = clr_edges();
+        let flows = edge_flows(solver, &edges).expect("max flow computation did not run");
+        let cut = solver
+            .assignment(0)
+            .expect("assignment computation did not run");
+        assert!(verify(&edges, &flows, &cut, 0, 5).is_valid());
+
+        let decomposition = decompose(solver, &edges, 0, 5).unwrap();
+        assert_eq!(decomposition.flow_value(), 23);
+        for (path, amount) in &decomposition.paths {
+            assert!(*amount > 0);
+            assert_eq!(path.first(), Some(&0));
+            assert_eq!(path.last(), Some(&5));
+        }
+    }
+
+    #[test]
+    fn decompose_dinic() {
+        let mut solver = Dinic::from_edge_list(clr_edges(), 0, 5);
+        solver.run();
+        check_solver(&solver);
+    }
+
+    #[test]
+    fn decompose_edmonds_karp() {
+        let mut solver = EdmondsKarp::from_edge_list(clr_edges(), 0, 5);
+        solver.run();
+        check_solver(&solver);
+    }
+
+    #[test]
+    fn decompose_ford_fulkerson() {
+        let mut solver = FordFulkerson::from_edge_list(clr_edges(), 0, 5);
+        solver.run();
+        check_solver(&solver);
+    }
+
+    #[test]
+    fn not_computed() {
+        let solver = Dinic::from_edge_list(clr_edges(), 0, 5);
+        assert!(decompose(&solver, &clr_edges(), 0, 5).is_err());
+    }
+
+    #[test]
+    fn paths_and_cycles() {
+        let edges = vec![
+            InputEdge::new(0, 1, ResidualCapacity::new(3)),
+            InputEdge::new(1, 2, ResidualCapacity::new(5)),
+            InputEdge::new(2, 3, ResidualCapacity::new(2)),
+            InputEdge::new(3, 1, ResidualCapacity::new(2)),
+            InputEdge::new(2, 4, ResidualCapacity::new(3)),
+            InputEdge::new(5, 6, ResidualCapacity::new(1)),
+            InputEdge::new(6, 5, ResidualCapacity::new(1)),
+        ];
+        let flows = vec![3, 5, 2, 2, 3, 1, 1];
+        let decomposition = decompose_flows(&edges, &flows, 0, 4);
+        assert_eq!(decomposition.paths, vec![(vec![0, 1, 2, 4], 3)]);
+        assert_eq!(
+            decomposition.cycles,
+            vec![(vec![1, 2, 3, 1], 2), (vec![5, 6, 5], 1)]
+        );
+    }
+}
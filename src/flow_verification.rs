@@ -0,0 +1,263 @@
+//! Verification of max-flow certificates. Given the input edges, a flow value
+//! for each of these edges and a cut assignment, the flow is checked to be
+//! feasible, i.e. respecting capacity constraints and flow conservation, and
+//! to be maximum by comparing its value to the capacity of the cut. By the
+//! max-flow min-cut theorem both values coincide if and only if the flow is
+//! maximum and the cut is minimum.
+use std::fmt::Display;
+
+use bitvec::vec::BitVec;
+use core::cmp::max;
+
+use crate::{edge::InputEdge, graph::NodeID, max_flow::ResidualCapacity};
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Violation {
+    /// The number of flow values does not match the number of edges
+    EdgeCountMismatch { edges: usize, flows: usize },
+    /// An edge carries a negative amount of flow
+    NegativeFlow { edge: usize, flow: i32 },
+    /// An edge carries more flow than its capacity
+    CapacityExceeded {
+        edge: usize,
+        flow: i32,
+        capacity: i32,
+    },
+    /// Inflow and outflow of a node other than source and target differ
+    FlowNotConserved { node: NodeID, excess: i64 },
+    /// The source is not on the source side of the cut
+    SourceNotInCut,
+    /// The target is on the source side of the cut
+    TargetInCut,
+    /// Value of the flow and capacity of the cut differ
+    CutCapacityMismatch { flow_value: i64, cut_capacity: i64 },
+}
+
+impl Display for Violation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Violation::EdgeCountMismatch { edges, flows } => {
+                write!(f, "{flows} flow values given for {edges} edges")
+            }
+            Violation::NegativeFlow { edge, flow } => {
+                write!(f, "edge {edge} carries negative flow {flow}")
+            }
+            Violation::CapacityExceeded {
+                edge,
+                flow,
+                capacity,
+            } => write!(
+                f,
+                "edge {edge} carries flow {flow} above capacity {capacity}"
+            ),
+            Violation::FlowNotConserved { node, excess } => {
+                write!(f, "node {node} has excess {excess}")
+            }
+            Violation::SourceNotInCut => write!(f, "source is not on the source side of the cut"),
+            Violation::TargetInCut => write!(f, "target is on the source side of the cut"),
+            Violation::CutCapacityMismatch {
+                flow_value,
+                cut_capacity,
+            } => write!(
+                f,
+                "flow value {flow_value} differs from cut capacity {cut_capacity}"
+            ),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct VerificationReport {
+    pub flow_value: i64,
+    pub cut_capacity: i64,
+    pub violations: Vec<Violation>,
+}
+
+impl VerificationReport {
+    /// Returns whether flow and cut form a valid certificate of optimality
+    pub fn is_valid(&self) -> bool {
+        self.violations.is_empty()
+    }
+}
+
+/// Verifies a max-flow certificate
+///
+/// # Arguments
+///
+/// * `edges` - the input edges carrying their capacities
+/// * `flows` - the flow on each of the input edges, in the same order
+/// * `cut` - the cut assignment, with nodes on the source side set to true
+/// * `source` - the source of the flow
+/// * `target` - the target of the flow
+pub fn verify(
+    edges: &[InputEdge<ResidualCapacity>],
+    flows: &[i32],
+    cut: &BitVec,
+    source: NodeID,
+    target: NodeID,
+) -> VerificationReport {
+    let mut violations = Vec::new();
+    if edges.len() != flows.len() {
+        violations.push(Violation::EdgeCountMismatch {
+            edges: edges.len(),
+            flows: flows.len(),
+        });
+        return VerificationReport {
+            flow_value: 0,
+            cut_capacity: 0,
+            violations,
+        };
+    }
+
+    let number_of_nodes = edges.iter().fold(max(source, target) + 1, |acc, edge| {
+        max(acc, max(edge.source, edge.target) + 1)
+    });
+    // nodes beyond the length of the cut vector are on the target side
+    let in_cut = |node: NodeID| node < cut.len() && cut[node];
+
+    let mut excess = vec![0i64; number_of_nodes];
+    let mut cut_capacity = 0;
+    for (index, (edge, flow)) in edges.iter().zip(flows).enumerate() {
+        if *flow < 0 {
+            violations.push(Violation::NegativeFlow {
+                edge: index,
+                flow: *flow,
+            });
+        }
+        if *flow > edge.data.capacity {
+            violations.push(Violation::CapacityExceeded {
+                edge: index,
+                flow: *flow,
+                capacity: edge.data.capacity,
+            });
+        }
+        excess[edge.source] -= *flow as i64;
+        excess[edge.target] += *flow as i64;
+
+        if in_cut(edge.source) && !in_cut(edge.target) {
+            cut_capacity += edge.data.capacity as i64;
+        }
+    }
+
+    for (node, excess) in excess.iter().enumerate() {
+        if node != source && node != target && *excess != 0 {
+            violations.push(Violation::FlowNotConserved {
+                node,
+                excess: *excess,
+            });
+        }
+    }
+
+    if !in_cut(source) {
+        violations.push(Violation::SourceNotInCut);
+    }
+    if in_cut(target) {
+        violations.push(Violation::TargetInCut);
+    }
+
+    // the value of the flow is the net outflow of the source
+    let flow_value = -excess[source];
+    if flow_value != cut_capacity {
+        violations.push(Violation::CutCapacityMismatch {
+            flow_value,
+            cut_capacity,
+        });
+    }
+
+    VerificationReport {
+        flow_value,
+        cut_capacity,
+        violations,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bitvec::{bits, prelude::Lsb0, vec::BitVec};
+
+    use crate::{
+        flow_verification::{verify, Violation},
+        test_utils::clr_edges,
+    };
+
+    fn clr_cut() -> BitVec {
+        bits![1, 1, 1, 0, 1, 0].to_bitvec()
+    }
+
+    #[test]
+    fn valid_certificate() {
+        let flows = vec![12, 11, 0, 12, 0, 11, 0, 19, 7, 4];
+        let report = verify(&clr_edges(), &flows, &clr_cut(), 0, 5);
+        assert!(report.is_valid());
+        assert_eq!(report.flow_value, 23);
+        assert_eq!(report.cut_capacity, 23);
+    }
+
+    #[test]
+    fn capacity_exceeded() {
+        let flows = vec![12, 11, 0, 12, 0, 11, 0, 20, 8, 3];
+        let report = verify(&clr_edges(), &flows, &clr_cut(), 0, 5);
+        assert!(!report.is_valid());
+        assert_eq!(
+            report.violations,
+            vec![Violation::CapacityExceeded {
+                edge: 8,
+                flow: 8,
+                capacity: 7
+            }]
+        );
+    }
+
+    #[test]
+    fn flow_not_conserved() {
+        let flows = vec![12, 11, 0, 12, 0, 10, 0, 19, 7, 4];
+        let report = verify(&clr_edges(), &flows, &clr_cut(), 0, 5);
+        assert_eq!(
+            report.violations,
+            vec![
+                Violation::FlowNotConserved { node: 2, excess: 1 },
+                Violation::FlowNotConserved {
+                    node: 4,
+                    excess: -1
+                }
+            ]
+        );
+    }
+
+    #[test]
+    fn not_a_minimum_cut() {
+        let flows = vec![12, 11, 0, 12, 0, 11, 0, 19, 7, 4];
+        let cut = bits![1, 0, 0, 0, 0, 0].to_bitvec();
+        let report = verify(&clr_edges(), &flows, &cut, 0, 5);
+        assert_eq!(
+            report.violations,
+            vec![Violation::CutCapacityMismatch {
+                flow_value: 23,
+                cut_capacity: 29
+            }]
+        );
+    }
+
+    #[test]
+    fn invalid_cut_sides() {
+        let flows = vec![0; 10];
+        let cut = bits![0, 0, 0, 0, 0, 1].to_bitvec();
+        let report = verify(&clr_edges(), &flows, &cut, 0, 5);
+        assert_eq!(
+            report.violations,
+            vec![Violation::SourceNotInCut, Violation::TargetInCut]
+        );
+    }
+
+    #[test]
+    fn edge_count_mismatch() {
+        let report = verify(&clr_edges(), &[1, 2, 3], &clr_cut(), 0, 5);
+        assert_eq!(
+            report.violations,
+            vec![Violation::EdgeCountMismatch {
+                edges: 10,
+                flows: 3
+            }]
+        );
+    }
+}
@@ -0,0 +1,397 @@
+//! Fiduccia-Mattheyses refinement of bisections. Each pass tentatively moves
+//! the unlocked node of highest gain from one side to the other, locks it and
+//! updates the gains of its neighbors, even if the cut gets worse in between.
+//! Afterwards, the pass is rolled back to the prefix of moves with the best
+//! cut. Gains are kept in ordered sets, whose size does not depend on the
+//! edge weights, so selecting a node takes logarithmic time.
+use bitvec::vec::BitVec;
+use core::cmp::max;
+use itertools::Itertools;
+use log::debug;
+use std::collections::BTreeSet;
+
+use crate::{
+    edge::{Edge, EdgeCapacity},
+    graph::NodeID,
+    inertial_flow::FlowResult,
+    multilevel::Level,
+    renumbering_table::RenumberingTable,
+};
+
+/// maximum number of passes over the nodes
+const MAXIMUM_PASSES: usize = 8;
+/// a pass ends after this many moves without finding a better cut
+const MAXIMUM_FRUITLESS_MOVES: usize = 100;
+
+/// The nodes of one side ordered by gain. Among nodes of equal gain, the one
+/// inserted last comes first, like in a bucket list.
+struct GainQueue {
+    // gain, insertion stamp and node
+    queue: BTreeSet<(i64, usize, NodeID)>,
+    gains: Vec<i64>,
+    stamps: Vec<usize>,
+    next_stamp: usize,
+}
+
+impl GainQueue {
+    fn new(number_of_nodes: usize) -> Self {
+        Self {
+            queue: BTreeSet::new(),
+            gains: vec![0; number_of_nodes],
+            stamps: vec![0; number_of_nodes],
+            next_stamp: 0,
+        }
+    }
+
+    fn insert(&mut self, node: NodeID, gain: i64) {
+        self.gains[node] = gain;
+        self.stamps[node] = self.next_stamp;
+        self.next_stamp += 1;
+        self.queue.insert((gain, self.stamps[node], node));
+    }
+
+    fn remove(&mut self, node: NodeID) {
+        self.queue
+            .remove(&(self.gains[node], self.stamps[node], node));
+    }
+
+    fn update(&mut self, node: NodeID, gain: i64) {
+        self.remove(node);
+        self.insert(node, gain);
+    }
+
+    /// Returns a node of highest gain and its gain
+    fn peek(&self) -> Option<(NodeID, i64)> {
+        self.queue.last().map(|(gain, _, node)| (*node, *gain))
+    }
+}
+
+/// Refines the bisection of a weighted graph in place. Nodes set in the
+/// assignment are on side 1, the others on side 0. Moves never shrink a side
+/// below its minimum weight, and among cuts of equal weight the one closest to
+/// the ratio of the minimum weights is preferred. Returns the reduction of the
+/// cut weight.
+pub fn refine_assignment(
+    level: &Level,
+    assignment: &mut BitVec,
+    minimum_weights: [usize; 2],
+) -> i64 {
+    let mut side_weights = [0, 0];
+    for u in 0..level.number_of_nodes() {
+        side_weights[assignment[u] as usize] += level.node_weights[u];
+    }
+
+    let mut total_gain = 0;
+    for _ in 0..MAXIMUM_PASSES {
+        let Some(gain) = fm_pass(level, assignment, &mut side_weights, minimum_weights) else {
+            break;
+        };
+        total_gain += gain;
+    }
+    total_gain
+}
+
+/// Runs a single pass and returns its gain, or None if the pass did not
+/// change the assignment.
+fn fm_pass(
+    level: &Level,
+    assignment: &mut BitVec,
+    side_weights: &mut [usize; 2],
+    minimum_weights: [usize; 2],
+) -> Option<i64> {
+    let number_of_nodes = level.number_of_nodes();
+    let mut queues = [
+        GainQueue::new(number_of_nodes),
+        GainQueue::new(number_of_nodes),
+    ];
+    let mut locked: BitVec = BitVec::repeat(false, number_of_nodes);
+    for u in 0..number_of_nodes {
+        let side = assignment[u] as usize;
+        let gain = level.adjacency[u]
+            .iter()
+            .map(|(v, weight)| {
+                if assignment[*v] as usize == side {
+                    -(*weight as i64)
+                } else {
+                    *weight as i64
+                }
+            })
+            .sum();
+        queues[side].insert(u, gain);
+    }
+
+    // deviation of the side weights from the ratio of the minimum weights
+    let shares = if minimum_weights == [0, 0] {
+        [1, 1]
+    } else {
+        minimum_weights
+    };
+    let imbalance = |side_weights: &[usize; 2]| {
+        (side_weights[0] * shares[1]).abs_diff(side_weights[1] * shares[0])
+    };
+    let mut moves = Vec::new();
+    let mut cumulative_gain = 0;
+    // gain, imbalance and number of moves of the best prefix
+    let mut best = (0, imbalance(side_weights), 0);
+    loop {
+        // the feasible move of highest gain, preferring the heavier side
+        let candidate = (0..2)
+            .filter_map(|side| {
+                queues[side]
+                    .peek()
+                    .filter(|(node, _)| {
+                        side_weights[side] >= minimum_weights[side] + level.node_weights[*node]
+                    })
+                    .map(|(node, gain)| (gain, side_weights[side], side, node))
+            })
+            .max();
+        let Some((gain, _, from, node)) = candidate else {
+            break;
+        };
+
+        queues[from].remove(node);
+        locked.set(node, true);
+        assignment.set(node, from == 0);
+        side_weights[from] -= level.node_weights[node];
+        side_weights[1 - from] += level.node_weights[node];
+        cumulative_gain += gain;
+        moves.push(node);
+
+        for (v, weight) in &level.adjacency[node] {
+            if locked[*v] {
+                continue;
+            }
+            let side = assignment[*v] as usize;
+            // edges to the former side become external, those to the new side internal
+            let delta = if side == from { 2 } else { -2 } * *weight as i64;
+            let gain = queues[side].gains[*v] + delta;
+            queues[side].update(*v, gain);
+        }
+
+        let current = (cumulative_gain, imbalance(side_weights));
+        if current.0 > best.0 || (current.0 == best.0 && current.1 < best.1) {
+            best = (current.0, current.1, moves.len());
+        } else if moves.len() - best.2 > MAXIMUM_FRUITLESS_MOVES {
+            break;
+        }
+    }
+
+    // roll back all moves after the best prefix
+    for node in moves.drain(best.2..).rev() {
+        let from = assignment[node] as usize;
+        assignment.set(node, from == 0);
+        side_weights[from] -= level.node_weights[node];
+        side_weights[1 - from] += level.node_weights[node];
+    }
+
+    if moves.is_empty() {
+        return None;
+    }
+    Some(best.0)
+}
+
+/// Refines a bisection of the graph induced by the left and right ids of a
+/// cut. Either side keeps at least the given share of nodes, unless it was
+/// already smaller before.
+///
+/// # Arguments
+///
+/// * `input_edges` - a list of edges that represents the input graph
+/// * `result` - the cut to refine
+/// * `balance_factor` - the minimum share of nodes on either side of the cut
+pub fn refine<E: Edge<ID = NodeID> + EdgeCapacity>(
+    input_edges: &[E],
+    result: &FlowResult,
+    balance_factor: f64,
+) -> FlowResult {
+    refine_with_ratio(input_edges, result, balance_factor, 0.5)
+}
+
+/// Refines a bisection of the graph induced by the left and right ids of a
+/// cut that targets an uneven split. The minimum share of each side is scaled
+/// by its targeted share, so that refinement does not pull the cut back
+/// towards an even split.
+///
+/// # Arguments
+///
+/// * `input_edges` - a list of edges that represents the input graph
+/// * `result` - the cut to refine
+/// * `balance_factor` - the minimum share of nodes on either side of an even
+///   cut
+/// * `ratio` - the targeted share of nodes on the left side of the cut
+pub fn refine_with_ratio<E: Edge<ID = NodeID> + EdgeCapacity>(
+    input_edges: &[E],
+    result: &FlowResult,
+    balance_factor: f64,
+    ratio: f64,
+) -> FlowResult {
+    debug_assert!(ratio > 0. && ratio < 1.);
+    let node_id_list = result
+        .left_ids
+        .iter()
+        .chain(result.right_ids.iter())
+        .copied()
+        .collect_vec();
+    let number_of_nodes = node_id_list.len();
+    let universe_size = input_edges.iter().fold(0, |acc, edge| {
+        max(acc, max(edge.source(), edge.target()) + 1)
+    });
+    let universe_size = node_id_list
+        .iter()
+        .fold(universe_size, |acc, id| max(acc, id + 1));
+
+    let mut renumbering_table =
+        RenumberingTable::new_with_size_hint(universe_size, number_of_nodes);
+    for (local_id, id) in node_id_list.iter().enumerate() {
+        renumbering_table.set(*id, local_id);
+    }
+    let edges = input_edges
+        .iter()
+        .filter(|edge| {
+            renumbering_table.contains_key(edge.source())
+                && renumbering_table.contains_key(edge.target())
+        })
+        .map(|edge| {
+            (
+                renumbering_table.get(edge.source()),
+                renumbering_table.get(edge.target()),
+                usize::try_from(edge.capacity()).expect("negative edge capacity"),
+            )
+        })
+        .collect_vec();
+
+    let level = Level::new(vec![1; number_of_nodes], edges.iter().copied());
+    // nodes on the right side are set
+    let mut assignment: BitVec = (0..number_of_nodes)
+        .map(|local_id| local_id >= result.left_ids.len())
+        .collect();
+    // nodes on the left side are not set and thus on side 0
+    let minimum_weight =
+        |share: f64| (balance_factor * 2. * share * number_of_nodes as f64).ceil() as usize;
+    let minimum_weights = [minimum_weight(ratio), minimum_weight(1. - ratio)];
+    let gain = refine_assignment(&level, &mut assignment, minimum_weights);
+    debug!("refinement reduced the cut weight by {gain}");
+
+    let flow = edges
+        .iter()
+        .filter(|(u, v, _)| !assignment[*u] && assignment[*v])
+        .map(|(_, _, capacity)| *capacity as i32)
+        .sum();
+    let (right_ids, left_ids): (Vec<_>, Vec<_>) =
+        node_id_list
+            .iter()
+            .enumerate()
+            .partition_map(|(local_id, id)| {
+                if assignment[local_id] {
+                    itertools::Either::Left(*id)
+                } else {
+                    itertools::Either::Right(*id)
+                }
+            });
+    let balance = std::cmp::min(left_ids.len(), right_ids.len()) as f64 / number_of_nodes as f64;
+
+    FlowResult {
+        flow,
+        balance,
+        left_ids,
+        right_ids,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bitvec::vec::BitVec;
+    use itertools::Itertools;
+
+    use crate::{
+        edge::InputEdge,
+        fm_refinement::{refine, refine_assignment, refine_with_ratio},
+        inertial_flow::FlowResult,
+        multilevel::Level,
+        test_utils::{dumbbell, grid},
+    };
+
+    #[test]
+    fn swapped_nodes() {
+        // the corner nodes 0 and 31 are on the wrong sides
+        let left_ids = (1..16).chain([31]).collect_vec();
+        let right_ids = (16..31).chain([0]).collect_vec();
+        let result = FlowResult {
+            flow: 5,
+            balance: 0.5,
+            left_ids,
+            right_ids,
+        };
+
+        let refined = refine(&dumbbell(4), &result, 0.25);
+        assert_eq!(refined.flow, 1);
+        assert_eq!(refined.balance, 0.5);
+        let mut left_ids = refined.left_ids;
+        left_ids.sort();
+        assert!(left_ids == (0..16).collect_vec() || left_ids == (16..32).collect_vec());
+    }
+
+    #[test]
+    fn large_capacities() {
+        // the memory of a pass does not grow with the capacities
+        let capacity = 100_000_000;
+        let edges = dumbbell(4)
+            .iter()
+            .map(|edge| InputEdge::new(edge.source, edge.target, capacity))
+            .collect_vec();
+        let result = FlowResult {
+            flow: 5 * capacity,
+            balance: 0.5,
+            left_ids: (1..16).chain([31]).collect_vec(),
+            right_ids: (16..31).chain([0]).collect_vec(),
+        };
+
+        let refined = refine(&edges, &result, 0.25);
+        assert_eq!(refined.flow, capacity);
+        assert_eq!(refined.balance, 0.5);
+    }
+
+    #[test]
+    fn balance_constraint() {
+        // the optimal cut would move all nodes to one side
+        let edges = dumbbell(4);
+        let level = Level::new(
+            vec![1; 32],
+            edges.iter().map(|edge| (edge.source, edge.target, 1)),
+        );
+        let mut assignment: BitVec = (0..32).map(|u| u >= 24).collect();
+        let cut_before = level.cut_weight(&assignment);
+        let gain = refine_assignment(&level, &mut assignment, [8, 8]);
+
+        assert!(assignment.count_ones() >= 8);
+        assert!(assignment.count_zeros() >= 8);
+        assert_eq!(
+            level.cut_weight(&assignment) as i64,
+            cut_before as i64 - gain
+        );
+        assert!(level.cut_weight(&assignment) < cut_before);
+    }
+
+    #[test]
+    fn uneven_ratio() {
+        // a grid of 16 rows and 4 columns, where all cuts between rows are
+        // equally small, that is cut after its fourth row
+        let edges = grid(4, 16);
+        let result = FlowResult {
+            flow: 4,
+            balance: 0.25,
+            left_ids: (0..16).collect_vec(),
+            right_ids: (16..64).collect_vec(),
+        };
+
+        // the targeted split is kept among the cuts of equal weight
+        let refined = refine_with_ratio(&edges, &result, 0.25, 0.25);
+        assert_eq!(refined.flow, 4);
+        assert_eq!(refined.left_ids.len(), 16);
+
+        // while an even refinement moves the cut to the middle
+        let refined = refine(&edges, &result, 0.25);
+        assert_eq!(refined.flow, 4);
+        assert_eq!(refined.left_ids.len(), 32);
+    }
+}
@@ -2,7 +2,7 @@ use crate::bfs::BFS;
 use crate::{
     edge::{Edge, InputEdge},
     graph::{Graph, NodeID},
-    max_flow::{MaxFlow, ResidualCapacity},
+    max_flow::{check_instance, MaxFlow, MaxFlowError, ResidualCapacity},
     static_graph::StaticGraph,
 };
 use bitvec::vec::BitVec;
@@ -15,7 +15,7 @@ use std::time::Instant;
 pub struct FordFulkerson {
     residual_graph: StaticGraph<ResidualCapacity>,
     max_flow: i32,
-    finished: bool,
+    status: Result<(), MaxFlowError>,
     source: NodeID,
     target: NodeID,
     bound: Option<Arc<AtomicI32>>,
@@ -80,7 +80,7 @@ impl FordFulkerson {
         Self {
             residual_graph: StaticGraph::new(edge_list),
             max_flow: 0,
-            finished: false,
+            status: Err(MaxFlowError::NotRun),
             source,
             target,
             bound: None,
@@ -96,6 +96,10 @@ impl MaxFlow for FordFulkerson {
     }
 
     fn run(&mut self) {
+        self.status = check_instance(&self.residual_graph, self.source, self.target);
+        if self.status.is_err() {
+            return;
+        }
         let mut bfs = BFS::new(
             &[self.source],
             &[self.target],
@@ -142,19 +146,18 @@ impl MaxFlow for FordFulkerson {
             debug!(" flow assignment3 took: {:?} (done)", duration);
         }
 
-        self.finished = true;
+        self.status = Ok(());
     }
 
-    fn max_flow(&self) -> Result<i32, String> {
-        if !self.finished {
-            return Err("Assigment was not computed.".to_string());
-        }
+    fn max_flow(&self) -> Result<i32, MaxFlowError> {
+        self.status?;
         Ok(self.max_flow)
     }
 
-    fn assignment(&self, source: NodeID) -> Result<BitVec, String> {
-        if !self.finished {
-            return Err("Assigment was not computed.".to_string());
+    fn assignment(&self, source: NodeID) -> Result<BitVec, MaxFlowError> {
+        self.status?;
+        if source >= self.residual_graph.number_of_nodes() {
+            return Err(MaxFlowError::InvalidSource(source));
         }
 
         // run a reachability analysis
@@ -175,14 +178,21 @@ impl MaxFlow for FordFulkerson {
         }
         Ok(reachable)
     }
+
+    fn residual_graph(&self) -> &StaticGraph<ResidualCapacity> {
+        &self.residual_graph
+    }
 }
 
 #[cfg(test)]
 mod tests {
 
+    use std::sync::{atomic::AtomicI32, Arc};
+
     use crate::edge::InputEdge;
     use crate::ford_fulkerson::FordFulkerson;
     use crate::max_flow::MaxFlow;
+    use crate::max_flow::MaxFlowError;
     use crate::max_flow::ResidualCapacity;
     use bitvec::bits;
     use bitvec::prelude::Lsb0;
@@ -369,4 +379,59 @@ mod tests {
             .assignment(0)
             .expect("assignment computation did not run");
     }
+
+    #[test]
+    fn error_not_run() {
+        let edges = vec![InputEdge::new(0, 1, ResidualCapacity::new(7))];
+        let max_flow_solver = FordFulkerson::from_edge_list(edges, 0, 1);
+        assert_eq!(max_flow_solver.max_flow(), Err(MaxFlowError::NotRun));
+        assert_eq!(max_flow_solver.assignment(0), Err(MaxFlowError::NotRun));
+    }
+
+    #[test]
+    fn error_empty_graph() {
+        let mut max_flow_solver = FordFulkerson::from_edge_list(Vec::new(), 0, 1);
+        max_flow_solver.run();
+        assert_eq!(max_flow_solver.max_flow(), Err(MaxFlowError::EmptyGraph));
+    }
+
+    #[test]
+    fn error_invalid_source_and_target() {
+        let edges = vec![
+            InputEdge::new(0, 1, ResidualCapacity::new(7)),
+            InputEdge::new(1, 2, ResidualCapacity::new(3)),
+        ];
+        for (source, target, error) in [
+            (5, 2, MaxFlowError::InvalidSource(5)),
+            (0, 5, MaxFlowError::InvalidTarget(5)),
+            (1, 1, MaxFlowError::InvalidTarget(1)),
+        ] {
+            let mut max_flow_solver = FordFulkerson::from_edge_list(edges.clone(), source, target);
+            max_flow_solver.run();
+            assert_eq!(max_flow_solver.max_flow(), Err(error));
+            assert_eq!(max_flow_solver.assignment(source), Err(error));
+        }
+
+        let mut max_flow_solver = FordFulkerson::from_edge_list(edges, 0, 2);
+        max_flow_solver.run();
+        assert_eq!(max_flow_solver.max_flow(), Ok(3));
+        assert_eq!(
+            max_flow_solver.assignment(3),
+            Err(MaxFlowError::InvalidSource(3))
+        );
+    }
+
+    #[test]
+    fn upper_bound_is_discarded() {
+        // unlike Dinic, the solver does not abort once the flow exceeds the bound
+        let edges = vec![
+            InputEdge::new(0, 1, ResidualCapacity::new(7)),
+            InputEdge::new(0, 2, ResidualCapacity::new(3)),
+            InputEdge::new(1, 3, ResidualCapacity::new(6)),
+            InputEdge::new(2, 3, ResidualCapacity::new(8)),
+        ];
+        let mut max_flow_solver = FordFulkerson::from_edge_list(edges, 0, 3);
+        max_flow_solver.run_with_upper_bound(Arc::new(AtomicI32::new(5)));
+        assert_eq!(max_flow_solver.max_flow(), Ok(9));
+    }
 }
@@ -8,6 +8,28 @@ pub enum InputFormat {
     Dimacs,
     Ddsg,
     Metis,
+    /// OpenStreetMap XML or PBF, which contains graph and coordinates
+    Osm,
+}
+
+#[derive(ArgEnum, Clone, Debug)]
+pub enum OutputFormat {
+    Toolbox,
+    Dimacs,
+    Ddsg,
+    Metis,
+}
+
+impl OutputFormat {
+    /// the extension that is appended to the input file names
+    pub fn extension(&self) -> &'static str {
+        match self {
+            OutputFormat::Toolbox => "toolbox",
+            OutputFormat::Dimacs => "dimacs",
+            OutputFormat::Ddsg => "ddsg",
+            OutputFormat::Metis => "metis",
+        }
+    }
 }
 
 #[derive(Parser, Debug)]
@@ -23,13 +45,21 @@ pub struct Arguments {
 
     /// path to the input coordinates
     #[clap(short, long, action)]
-    pub coordinates: String,
+    pub coordinates: Option<String>,
+
+    /// format of the output files, which are named after the input files
+    #[clap(short, long, arg_enum, default_value_t = OutputFormat::Toolbox)]
+    pub output_format: OutputFormat,
 }
 
 impl Display for Arguments {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         writeln!(f, "command line arguments:")?;
         writeln!(f, "graph: {}", self.graph)?;
-        writeln!(f, "coordinates: {}", self.coordinates)
+        match &self.coordinates {
+            Some(coordinates) => writeln!(f, "coordinates: {}", coordinates)?,
+            None => writeln!(f, "coordinates: none")?,
+        }
+        writeln!(f, "output format: {:?}", self.output_format)
     }
 }
@@ -1,12 +1,40 @@
 mod command_line;
-use std::{fs::File, io::BufWriter};
+use std::{
+    fs::File,
+    io::{self, BufWriter, Write},
+    process,
+};
 
 use bincode::serialize_into;
 use env_logger::Env;
-use log::info;
+use log::{error, info};
+use serde::Serialize;
 
-use crate::command_line::{Arguments, InputFormat};
-use toolbox_rs::{ddsg, dimacs, edge::InputEdge, metis};
+use crate::command_line::{Arguments, InputFormat, OutputFormat};
+use toolbox_rs::{
+    ddsg, dimacs, edge::InputEdge, geometry::primitives::FPCoordinate, io::ParseError, metis, osm,
+};
+
+/// Reports a malformed input file and exits with a failure code
+fn exit_with(error: ParseError) -> ! {
+    error!("could not read input: {error}");
+    process::exit(1);
+}
+
+/// Reports a failed write of an output file and exits with a failure code
+fn exit_on_write_error(filename: &str, result: io::Result<()>) {
+    if let Err(error) = result {
+        error!("could not write {filename}: {error}");
+        process::exit(1);
+    }
+}
+
+/// Writes any serializable value in the binary toolbox format
+fn write_toolbox<T: Serialize + ?Sized>(filename: &str, value: &T) -> io::Result<()> {
+    let mut f = BufWriter::new(File::create(filename)?);
+    serialize_into(&mut f, value).map_err(io::Error::other)?;
+    f.flush()
+}
 
 fn main() {
     env_logger::Builder::from_env(Env::default().default_filter_or("info")).init();
@@ -22,25 +50,55 @@ fn main() {
     let args = <Arguments as clap::Parser>::parse();
     info!("{args}");
 
+    // OpenStreetMap files contain the coordinates of the graph, too
+    let mut road_coordinates = None;
     let edges: Vec<InputEdge<i32>> = match args.input_format {
         InputFormat::Ddsg => ddsg::read_graph(&args.graph, ddsg::WeightType::Original),
         InputFormat::Dimacs => dimacs::read_graph(&args.graph, dimacs::WeightType::Original),
         InputFormat::Metis => metis::read_graph(&args.graph, metis::WeightType::Original),
-    };
+        InputFormat::Osm => osm::read_file(&args.graph).map(|network| {
+            road_coordinates = Some(network.coordinates);
+            network.edges
+        }),
+    }
+    .unwrap_or_else(|error| exit_with(error));
 
-    let coordinates = match args.input_format {
-        InputFormat::Ddsg => ddsg::read_coordinates(&args.coordinates),
-        InputFormat::Dimacs => dimacs::read_coordinates(&args.coordinates),
-        InputFormat::Metis => metis::read_coordinates(&args.coordinates),
+    let extension = args.output_format.extension();
+    info!("writing edges in {:?} format", args.output_format);
+    let filename = format!("{}.{extension}", args.graph);
+    let result = match args.output_format {
+        OutputFormat::Toolbox => write_toolbox(&filename, &edges),
+        OutputFormat::Dimacs => dimacs::write_graph(&filename, &edges),
+        OutputFormat::Ddsg => ddsg::write_graph(&filename, &edges),
+        OutputFormat::Metis => metis::write_graph(&filename, &edges),
     };
+    exit_on_write_error(&filename, result);
 
-    info!("writing edges into intermediate format");
-    let mut f = BufWriter::new(File::create(args.graph + ".toolbox").unwrap());
-    serialize_into(&mut f, &edges).unwrap();
-
-    info!("writing coordinates into intermediate format");
-    let mut f = BufWriter::new(File::create(args.coordinates + ".toolbox").unwrap());
-    serialize_into(&mut f, &coordinates).unwrap();
+    // graphs like social networks come without coordinates
+    let coordinates: Option<(Vec<FPCoordinate>, String)> = match road_coordinates {
+        Some(coordinates) => Some((coordinates, format!("{}.coordinates", args.graph))),
+        None => args.coordinates.map(|coordinates_file| {
+            let coordinates = match args.input_format {
+                InputFormat::Ddsg => ddsg::read_coordinates(&coordinates_file),
+                InputFormat::Dimacs => dimacs::read_coordinates(&coordinates_file),
+                InputFormat::Metis => metis::read_coordinates(&coordinates_file),
+                InputFormat::Osm => unreachable!("coordinates are read with the graph"),
+            }
+            .unwrap_or_else(|error| exit_with(error));
+            (coordinates, coordinates_file)
+        }),
+    };
+    if let Some((coordinates, coordinates_file)) = coordinates {
+        info!("writing coordinates in {:?} format", args.output_format);
+        let filename = format!("{coordinates_file}.{extension}");
+        let result = match args.output_format {
+            OutputFormat::Toolbox => write_toolbox(&filename, &coordinates),
+            OutputFormat::Dimacs => dimacs::write_coordinates(&filename, &coordinates),
+            OutputFormat::Ddsg => ddsg::write_coordinates(&filename, &coordinates),
+            OutputFormat::Metis => metis::write_coordinates(&filename, &coordinates),
+        };
+        exit_on_write_error(&filename, result);
+    }
 
     info!("done.");
 }
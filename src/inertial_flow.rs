@@ -1,5 +1,8 @@
 use std::{
     cmp::max,
+    collections::HashSet,
+    f64::consts::PI,
+    fmt::Display,
     ops::Index,
     sync::{atomic::AtomicI32, Arc},
 };
@@ -9,36 +12,123 @@ use log::debug;
 
 use crate::{
     dinic::Dinic,
-    edge::{InputEdge, TrivialEdge},
+    edge::{Edge, EdgeCapacity, InputEdge},
     geometry::primitives::FPCoordinate,
-    max_flow::{MaxFlow, ResidualCapacity},
+    graph::NodeID,
+    max_flow::{MaxFlow, MaxFlowError, ResidualCapacity},
     renumbering_table::RenumberingTable,
 };
 
-pub struct RotatedComparators([fn(i32, i32) -> i32; 4]);
-// comparator equivalent to rotation matrix at 0, 90, 180 and 270 degrees
+/// A direction along which coordinates are ordered, given by the coefficients
+/// of latitude and longitude.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Axis {
+    pub lat: f64,
+    pub lon: f64,
+}
+
+impl Axis {
+    pub fn new(lat: f64, lon: f64) -> Self {
+        Self { lat, lon }
+    }
+
+    /// Returns the unit direction that is rotated by the angle (in radians)
+    /// from the latitude axis towards the longitude axis
+    pub fn from_angle(angle: f64) -> Self {
+        Self::new(angle.cos(), angle.sin())
+    }
+
+    /// Returns the principal component of the coordinates of the given nodes,
+    /// i.e. the direction along which the coordinates vary the most
+    pub fn principal(node_id_list: &[usize], coordinates: &[FPCoordinate]) -> Self {
+        let count = max(1, node_id_list.len()) as f64;
+        let (lat_sum, lon_sum) = node_id_list.iter().fold((0., 0.), |acc, id| {
+            (
+                acc.0 + coordinates[*id].lat as f64,
+                acc.1 + coordinates[*id].lon as f64,
+            )
+        });
+        let (lat_mean, lon_mean) = (lat_sum / count, lon_sum / count);
+
+        // entries of the symmetric 2x2 covariance matrix
+        let (mut lat_lat, mut lat_lon, mut lon_lon) = (0., 0., 0.);
+        for id in node_id_list {
+            let lat = coordinates[*id].lat as f64 - lat_mean;
+            let lon = coordinates[*id].lon as f64 - lon_mean;
+            lat_lat += lat * lat;
+            lat_lon += lat * lon;
+            lon_lon += lon * lon;
+        }
+
+        // angle of the eigenvector of the largest eigenvalue
+        Self::from_angle(0.5 * f64::atan2(2. * lat_lon, lat_lat - lon_lon))
+    }
 
-impl Default for RotatedComparators {
+    /// Returns the position of a coordinate along the axis
+    pub fn project(&self, coordinate: &FPCoordinate) -> f64 {
+        self.lat * coordinate.lat as f64 + self.lon * coordinate.lon as f64
+    }
+}
+
+impl Display for Axis {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "({:.3}, {:.3})", self.lat, self.lon)
+    }
+}
+
+/// The axes along which inertial flow orders the coordinates. Indexing wraps
+/// around at the number of axes.
+pub struct Axes(Vec<Axis>);
+
+impl Default for Axes {
     fn default() -> Self {
         Self::new()
     }
 }
 
-impl RotatedComparators {
+impl Axes {
+    /// Returns the four axes at 0, 90, 45 and 135 degrees
     pub fn new() -> Self {
-        // Comparator functions use the follwoing coefficients: (0, 1), (1, 0), (1, 1), (-1, 1)])
-        RotatedComparators([
-            |lat, _lon| -> i32 { lat },
-            |_lat, lon| -> i32 { lon },
-            |lat, lon| -> i32 { lon + lat },
-            |lat, lon| -> i32 { -lon + lat },
+        // the axes use the following coefficients: (1, 0), (0, 1), (1, 1), (1, -1)
+        Axes(vec![
+            Axis::new(1., 0.),
+            Axis::new(0., 1.),
+            Axis::new(1., 1.),
+            Axis::new(1., -1.),
         ])
     }
+
+    /// Returns the given number of axes that are evenly spaced over 180 degrees
+    pub fn with_directions(count: usize) -> Self {
+        assert!(count > 0, "at least one direction is needed");
+        Axes(
+            (0..count)
+                .map(|i| Axis::from_angle(PI * i as f64 / count as f64))
+                .collect(),
+        )
+    }
+
+    /// Adds an axis, e.g. the principal axis of a cell
+    pub fn push(&mut self, axis: Axis) {
+        self.0.push(axis);
+    }
+
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    pub fn as_slice(&self) -> &[Axis] {
+        &self.0
+    }
 }
 
-impl Index<usize> for RotatedComparators {
-    type Output = fn(i32, i32) -> i32;
-    fn index(&self, i: usize) -> &fn(i32, i32) -> i32 {
+impl Index<usize> for Axes {
+    type Output = Axis;
+    fn index(&self, i: usize) -> &Axis {
         &self.0[i % self.0.len()]
     }
 }
@@ -59,47 +149,245 @@ pub fn flow_cmp(a: &FlowResult, b: &FlowResult) -> std::cmp::Ordering {
     a.flow.cmp(&b.flow)
 }
 
+/// Orders separators like `flow_cmp` orders cuts, i.e. by their size first
+/// and by their balance second
+pub fn separator_cmp(a: &SeparatorResult, b: &SeparatorResult) -> std::cmp::Ordering {
+    a.flow
+        .cmp(&b.flow)
+        .then_with(|| b.balance.partial_cmp(&a.balance).unwrap())
+}
+
+/// A balanced node separator. No edge connects the left and the right side
+/// directly, and the separator size is the number of separator nodes.
+pub struct SeparatorResult {
+    pub flow: i32,
+    pub balance: f64,
+    pub left_ids: Vec<usize>,
+    pub separator_ids: Vec<usize>,
+    pub right_ids: Vec<usize>,
+}
+
+impl SeparatorResult {
+    /// Derives a node separator from an edge cut by covering the cut edges
+    /// with nodes. Nodes that cover the most uncovered edges are chosen first.
+    pub fn from_cut<E: Edge<ID = NodeID>>(input_edges: &[E], cut: &FlowResult) -> Self {
+        let left: HashSet<NodeID> = cut.left_ids.iter().copied().collect();
+        let right: HashSet<NodeID> = cut.right_ids.iter().copied().collect();
+        // cut edges in either direction, each given by its left and right end
+        let cut_edges = input_edges
+            .iter()
+            .filter_map(|edge| {
+                let (u, v) = (edge.source(), edge.target());
+                if left.contains(&u) && right.contains(&v) {
+                    Some((u, v))
+                } else if right.contains(&u) && left.contains(&v) {
+                    Some((v, u))
+                } else {
+                    None
+                }
+            })
+            .sorted()
+            .dedup()
+            .collect_vec();
+
+        let incident_edges = cut_edges
+            .iter()
+            .enumerate()
+            .flat_map(|(edge, (u, v))| [(*u, edge), (*v, edge)])
+            .into_group_map();
+        let candidates = incident_edges
+            .keys()
+            .copied()
+            .sorted_by_key(|node| (std::cmp::Reverse(incident_edges[node].len()), *node))
+            .collect_vec();
+        let mut separator_ids = Vec::new();
+        let mut covered = vec![false; cut_edges.len()];
+        for node in candidates {
+            let mut covers_edge = false;
+            for edge in &incident_edges[&node] {
+                covers_edge |= !covered[*edge];
+                covered[*edge] = true;
+            }
+            if covers_edge {
+                separator_ids.push(node);
+            }
+        }
+
+        let separator: HashSet<NodeID> = separator_ids.iter().copied().collect();
+        let remove_separator = |ids: &[NodeID]| {
+            ids.iter()
+                .copied()
+                .filter(|id| !separator.contains(id))
+                .collect_vec()
+        };
+        let left_ids = remove_separator(&cut.left_ids);
+        let right_ids = remove_separator(&cut.right_ids);
+        Self::new(left_ids, separator_ids, right_ids)
+    }
+
+    fn new(left_ids: Vec<usize>, separator_ids: Vec<usize>, right_ids: Vec<usize>) -> Self {
+        let total = max(1, left_ids.len() + separator_ids.len() + right_ids.len());
+        Self {
+            flow: separator_ids.len() as i32,
+            balance: std::cmp::min(left_ids.len(), right_ids.len()) as f64 / total as f64,
+            left_ids,
+            separator_ids,
+            right_ids,
+        }
+    }
+
+    /// the result if the search was aborted or no separator exists
+    fn unseparated() -> Self {
+        Self {
+            flow: i32::MAX,
+            balance: 0.,
+            left_ids: Vec::new(),
+            separator_ids: Vec::new(),
+            right_ids: Vec::new(),
+        }
+    }
+}
+
+/// Sorts the node ids along the axis. The coordinates themselves are not touched.
+fn sort_along_axis(
+    node_id_list: &[usize],
+    coordinates: &[FPCoordinate],
+    axis: &Axis,
+) -> Vec<usize> {
+    let mut node_id_list = node_id_list.to_vec();
+    node_id_list.sort_unstable_by(|a, b| {
+        axis.project(&coordinates[*a])
+            .total_cmp(&axis.project(&coordinates[*b]))
+    });
+    node_id_list
+}
+
+/// Returns the number of nodes at either end of an ordering of the given
+/// length that are contracted into the source and the target.
+fn contraction_counts(number_of_nodes: usize, balance_factor: f64, ratio: f64) -> (usize, usize) {
+    let contraction = |share: f64| {
+        max(
+            1,
+            (number_of_nodes as f64 * balance_factor * 2. * share) as usize,
+        )
+    };
+    let (source_count, target_count) = (contraction(ratio), contraction(1. - ratio));
+    debug_assert!(source_count + target_count <= number_of_nodes);
+    (source_count, target_count)
+}
+
 /// Computes the inertial flow cut for a given orientation and balance
 ///
 /// # Arguments
 ///
-/// * `index` - which of the (0..4) substeps to execute
-/// * `edges` - a list of edges that represents the input graph
+/// * `input_edges` - a list of edges that represents the input graph, with
+///   their capacities
 /// * `node_id_list` - list of node ids
 /// * `coordinates` - immutable slice of coordinates of the graphs nodes
+/// * `axis` - the axis along which the coordinates are ordered
 /// * `balance_factor` - balance factor, i.e. how many nodes get contracted
 /// * `upper_bound` - a global upperbound to the best inertial flow cut
-pub fn sub_step(
-    input_edges: &[TrivialEdge],
+pub fn sub_step<E: Edge<ID = NodeID> + EdgeCapacity>(
+    input_edges: &[E],
     node_id_list: &[usize],
     coordinates: &[FPCoordinate],
-    axis: usize,
+    axis: &Axis,
     balance_factor: f64,
     upper_bound: Arc<AtomicI32>,
 ) -> FlowResult {
-    debug_assert!(axis < 4);
+    sub_step_with_ratio(
+        input_edges,
+        node_id_list,
+        coordinates,
+        axis,
+        balance_factor,
+        0.5,
+        upper_bound,
+    )
+}
+
+/// Computes the inertial flow cut for a given orientation and balance that
+/// targets an uneven split. The contracted sources and targets are scaled by
+/// the share of the respective side, so that the left side has roughly the
+/// given ratio of the nodes.
+///
+/// # Arguments
+///
+/// * `input_edges` - a list of edges that represents the input graph, with
+///   their capacities
+/// * `node_id_list` - list of node ids
+/// * `coordinates` - immutable slice of coordinates of the graphs nodes
+/// * `axis` - the axis along which the coordinates are ordered
+/// * `balance_factor` - balance factor, i.e. how many nodes get contracted
+/// * `ratio` - the targeted share of nodes on the left side of the cut
+/// * `upper_bound` - a global upperbound to the best inertial flow cut
+pub fn sub_step_with_ratio<E: Edge<ID = NodeID> + EdgeCapacity>(
+    input_edges: &[E],
+    node_id_list: &[usize],
+    coordinates: &[FPCoordinate],
+    axis: &Axis,
+    balance_factor: f64,
+    ratio: f64,
+    upper_bound: Arc<AtomicI32>,
+) -> FlowResult {
+    debug_assert!(ratio > 0.);
+    debug_assert!(ratio < 1.);
     debug_assert!(balance_factor > 0.);
     debug_assert!(balance_factor < 0.5);
     debug_assert!(coordinates.len() > 2);
 
-    let comparator = &RotatedComparators::new()[axis];
-    debug!("[{axis}] sorting cooefficient: {:?}", comparator);
-    // the iteration proxy list to be sorted. The coordinates vector itself is not touched.
-    let mut node_id_list = node_id_list.to_vec();
-    node_id_list
-        .sort_unstable_by_key(|a| -> i32 { comparator(coordinates[*a].lat, coordinates[*a].lon) });
+    debug!("[{axis}] sorting along axis");
+    let node_id_list = sort_along_axis(node_id_list, coordinates, axis);
+    ordered_sub_step(
+        input_edges,
+        &node_id_list,
+        balance_factor,
+        ratio,
+        upper_bound,
+    )
+}
 
-    let size_of_contraction = max(1, (node_id_list.len() as f64 * balance_factor) as usize);
-    let sources = &node_id_list[0..size_of_contraction as usize];
-    let targets = &node_id_list[node_id_list.len() - (size_of_contraction as usize)..];
+/// Computes the flow cut between both ends of an ordering of the nodes. The
+/// first and last nodes of the order are contracted into the source and the
+/// target, respectively. Any ordering that places nearby nodes close to each
+/// other works, e.g. a projection of the coordinates onto an axis or a
+/// spectral embedding.
+///
+/// # Arguments
+///
+/// * `input_edges` - a list of edges that represents the input graph, with
+///   their capacities
+/// * `node_id_list` - list of node ids in the order of the embedding
+/// * `balance_factor` - balance factor, i.e. how many nodes get contracted
+/// * `ratio` - the targeted share of nodes on the left side of the cut
+/// * `upper_bound` - a global upperbound to the best flow cut
+pub fn ordered_sub_step<E: Edge<ID = NodeID> + EdgeCapacity>(
+    input_edges: &[E],
+    node_id_list: &[usize],
+    balance_factor: f64,
+    ratio: f64,
+    upper_bound: Arc<AtomicI32>,
+) -> FlowResult {
+    debug_assert!(ratio > 0.);
+    debug_assert!(ratio < 1.);
+    debug_assert!(balance_factor > 0.);
+    debug_assert!(balance_factor < 0.5);
+    debug_assert!(node_id_list.len() > 2);
 
-    debug_assert!(!sources.is_empty());
-    debug_assert!(!targets.is_empty());
+    let (source_count, target_count) =
+        contraction_counts(node_id_list.len(), balance_factor, ratio);
+    let sources = &node_id_list[0..source_count];
+    let targets = &node_id_list[node_id_list.len() - target_count..];
 
-    debug!("[{axis}] renumbering of inertial flow graph");
-    // let mut renumbering_table = vec![usize::MAX; coordinates.len()];
+    debug!("renumbering of flow graph");
+    let universe_size = input_edges
+        .iter()
+        .flat_map(|edge| [edge.source(), edge.target()])
+        .chain(node_id_list.iter().copied())
+        .max()
+        .map_or(0, |id| id + 1);
     let mut renumbering_table =
-        RenumberingTable::new_with_size_hint(coordinates.len(), node_id_list.len());
+        RenumberingTable::new_with_size_hint(universe_size, node_id_list.len());
     // nodes in the in the graph have to be numbered consecutively.
     // the mapping is input id -> dinic id
 
@@ -115,9 +403,9 @@ pub fn sub_step(
         .iter()
         .map(|edge| -> InputEdge<ResidualCapacity> {
             InputEdge::<ResidualCapacity> {
-                source: edge.source,
-                target: edge.target,
-                data: ResidualCapacity::new(1),
+                source: edge.source(),
+                target: edge.target(),
+                data: ResidualCapacity::new(edge.capacity()),
             }
         })
         .collect_vec();
@@ -136,52 +424,54 @@ pub fn sub_step(
         e.source = renumbering_table.get(e.source);
         e.target = renumbering_table.get(e.target);
     }
-    debug!("[{axis}] instantiating min-cut solver, epsilon 0.25");
+    debug!("instantiating min-cut solver, epsilon 0.25");
 
     // remove eigenloops especially from contracted regions
     let edge_count_before = edges.len();
     edges.retain(|edge| edge.source != edge.target);
     debug!(
-        "[{axis}] eigenloop removal - edge count before {edge_count_before}, after {}",
+        "eigenloop removal - edge count before {edge_count_before}, after {}",
         edges.len()
     );
     edges.shrink_to_fit();
 
-    debug!("[{axis}] instantiating min-cut solver, epsilon {balance_factor}");
+    debug!("instantiating min-cut solver, epsilon {balance_factor}");
     let mut max_flow_solver = Dinic::from_edge_list(edges, 0, 1);
-    debug!("[{axis}] instantiated min-cut solver");
+    debug!("instantiated min-cut solver");
     max_flow_solver.run_with_upper_bound(upper_bound);
 
-    let max_flow = max_flow_solver.max_flow();
-
-    if max_flow.is_err() {
-        // Error is returned in case the search is aborted early
-        return FlowResult {
-            flow: i32::MAX,
-            balance: 0.,
-            left_ids: Vec::new(),
-            right_ids: Vec::new(),
-        };
-    }
-    let flow = max_flow.expect("max flow computation did not run");
+    let flow = match max_flow_solver.max_flow() {
+        Ok(flow) => flow,
+        Err(MaxFlowError::AbortedByUpperBound(bound)) => {
+            // the search is aborted early if it can't beat the best known cut
+            debug!("aborted by upper bound {bound}");
+            return FlowResult {
+                flow: i32::MAX,
+                balance: 0.,
+                left_ids: Vec::new(),
+                right_ids: Vec::new(),
+            };
+        }
+        Err(error) => panic!("max flow computation failed: {error}"),
+    };
 
-    debug!("[{axis}] computed max flow: {flow}");
+    debug!("computed max flow: {flow}");
     let intermediate_assignment = max_flow_solver
         .assignment(0)
         .expect("max flow computation did not run");
 
     // TODO: don't copy, but partition in place
     let (left_ids, right_ids): (Vec<_>, Vec<_>) = node_id_list
-        .into_iter()
-        .filter(|id| renumbering_table.contains_key(*id))
-        .partition(|id| intermediate_assignment[renumbering_table.get(*id)]);
+        .iter()
+        .filter(|id| renumbering_table.contains_key(**id))
+        .partition(|id| intermediate_assignment[renumbering_table.get(**id)]);
 
     debug_assert!(!left_ids.is_empty());
     debug_assert!(!right_ids.is_empty());
 
     let balance = std::cmp::min(left_ids.len(), right_ids.len()) as f64
         / (left_ids.len() + right_ids.len()) as f64;
-    debug!("[{axis}] balance: {balance}");
+    debug!("balance: {balance}");
 
     FlowResult {
         flow,
@@ -191,27 +481,202 @@ pub fn sub_step(
     }
 }
 
+/// Computes a balanced node separator for a given orientation. Each node is
+/// split into an in-node and an out-node that are connected by an arc of unit
+/// capacity, while the edges of the graph get unbounded capacity. A minimum
+/// cut of this network only consists of node arcs, i.e. the cut nodes
+/// separate the sources from the targets. Separators are only found if no
+/// source is adjacent to a target.
+///
+/// # Arguments
+///
+/// * `input_edges` - a list of edges that represents the input graph
+/// * `node_id_list` - list of node ids
+/// * `coordinates` - immutable slice of coordinates of the graphs nodes
+/// * `axis` - the axis along which the coordinates are ordered
+/// * `balance_factor` - balance factor, i.e. how many nodes get contracted
+/// * `upper_bound` - a global upperbound to the smallest separator
+pub fn separator_sub_step<E: Edge<ID = NodeID>>(
+    input_edges: &[E],
+    node_id_list: &[usize],
+    coordinates: &[FPCoordinate],
+    axis: &Axis,
+    balance_factor: f64,
+    upper_bound: Arc<AtomicI32>,
+) -> SeparatorResult {
+    separator_sub_step_with_ratio(
+        input_edges,
+        node_id_list,
+        coordinates,
+        axis,
+        balance_factor,
+        0.5,
+        upper_bound,
+    )
+}
+
+/// Computes a node separator along the axis that targets an uneven split.
+///
+/// # Arguments
+///
+/// * `input_edges` - a list of edges that represents the input graph
+/// * `node_id_list` - list of node ids
+/// * `coordinates` - immutable slice of coordinates of the graphs nodes
+/// * `axis` - the axis along which the coordinates are ordered
+/// * `balance_factor` - balance factor, i.e. how many nodes get contracted
+/// * `ratio` - the targeted share of nodes on the left side of the separator
+/// * `upper_bound` - a global upperbound to the smallest separator
+pub fn separator_sub_step_with_ratio<E: Edge<ID = NodeID>>(
+    input_edges: &[E],
+    node_id_list: &[usize],
+    coordinates: &[FPCoordinate],
+    axis: &Axis,
+    balance_factor: f64,
+    ratio: f64,
+    upper_bound: Arc<AtomicI32>,
+) -> SeparatorResult {
+    debug!("[{axis}] sorting along axis");
+    let node_id_list = sort_along_axis(node_id_list, coordinates, axis);
+    let (source_count, target_count) =
+        contraction_counts(node_id_list.len(), balance_factor, ratio);
+    let number_of_nodes = node_id_list.len();
+    let mut renumbering_table =
+        RenumberingTable::new_with_size_hint(coordinates.len(), number_of_nodes);
+    for (position, id) in node_id_list.iter().enumerate() {
+        renumbering_table.set(*id, position);
+    }
+
+    // the in-node and out-node of the node at a position in the sorted order.
+    // Sources are contracted into node 0 and targets into node 1.
+    let split = |position: usize| {
+        if position < source_count {
+            (0, 0)
+        } else if position >= number_of_nodes - target_count {
+            (1, 1)
+        } else {
+            (2 * position, 2 * position + 1)
+        }
+    };
+    let unbounded = number_of_nodes as i32 + 1;
+    let mut edges = (source_count..number_of_nodes - target_count)
+        .map(|position| {
+            let (node_in, node_out) = split(position);
+            InputEdge::new(node_in, node_out, ResidualCapacity::new(1))
+        })
+        .collect_vec();
+    edges.extend(
+        input_edges
+            .iter()
+            .filter(|edge| {
+                renumbering_table.contains_key(edge.source())
+                    && renumbering_table.contains_key(edge.target())
+            })
+            .map(|edge| {
+                let (_, source_out) = split(renumbering_table.get(edge.source()));
+                let (target_in, _) = split(renumbering_table.get(edge.target()));
+                InputEdge::new(source_out, target_in, ResidualCapacity::new(unbounded))
+            })
+            // remove eigenloops of the contracted regions
+            .filter(|edge| edge.source != edge.target),
+    );
+    if edges.is_empty() {
+        return SeparatorResult::unseparated();
+    }
+
+    debug!("[{axis}] instantiating min-cut solver on split nodes");
+    let mut max_flow_solver = Dinic::from_edge_list(edges, 0, 1);
+    max_flow_solver.run_with_upper_bound(upper_bound);
+    let flow = match max_flow_solver.max_flow() {
+        Ok(flow) => flow,
+        Err(MaxFlowError::AbortedByUpperBound(bound)) => {
+            debug!("[{axis}] aborted by upper bound {bound}");
+            return SeparatorResult::unseparated();
+        }
+        Err(error) => panic!("[{axis}] max flow computation failed: {error}"),
+    };
+    if flow >= unbounded {
+        debug!("[{axis}] sources and targets are adjacent");
+        return SeparatorResult::unseparated();
+    }
+    debug!("[{axis}] computed separator of size {flow}");
+
+    let reachable = max_flow_solver
+        .assignment(0)
+        .expect("max flow computation did not run");
+    let (mut left_ids, mut separator_ids, mut right_ids) = (Vec::new(), Vec::new(), Vec::new());
+    for (position, id) in node_id_list.iter().enumerate() {
+        let (node_in, node_out) = split(position);
+        if reachable[node_out] {
+            left_ids.push(*id);
+        } else if reachable[node_in] {
+            separator_ids.push(*id);
+        } else {
+            right_ids.push(*id);
+        }
+    }
+    debug_assert_eq!(separator_ids.len() as i32, flow);
+    SeparatorResult::new(left_ids, separator_ids, right_ids)
+}
+
 #[cfg(test)]
 mod tests {
     use itertools::Itertools;
     use std::sync::{atomic::AtomicI32, Arc};
 
     use crate::{
+        edge::{InputEdge, TrivialEdge},
         geometry::primitives::FPCoordinate,
-        inertial_flow::{sub_step, TrivialEdge},
+        inertial_flow::{
+            separator_sub_step, sub_step, sub_step_with_ratio, FlowResult, SeparatorResult,
+        },
     };
 
-    use super::RotatedComparators;
+    use super::{Axes, Axis};
 
     #[test]
     fn iterate_with_wrap() {
-        let comparators = RotatedComparators::new();
+        let axes = Axes::new();
 
         (0..4).zip(4..8).for_each(|indices| {
-            assert_eq!(comparators[indices.0], comparators[indices.1]);
+            assert_eq!(axes[indices.0], axes[indices.1]);
         });
     }
 
+    #[test]
+    fn evenly_spaced_directions() {
+        let axes = Axes::with_directions(6);
+        assert_eq!(axes.len(), 6);
+        assert_eq!(axes[0], Axis::new(1., 0.));
+        for i in 0..6 {
+            let (a, b) = (axes[i], axes[i + 1]);
+            // consecutive unit directions are 30 degrees apart
+            assert!((a.lat * a.lat + a.lon * a.lon - 1.).abs() < 1e-9);
+            let angle = (a.lat * b.lat + a.lon * b.lon).abs().acos();
+            assert!((angle - std::f64::consts::PI / 6.).abs() < 1e-9);
+        }
+    }
+
+    #[test]
+    fn principal_axis() {
+        // points scattered around the diagonal through the origin
+        let coordinates = vec![
+            FPCoordinate::new(0, 1),
+            FPCoordinate::new(10, 9),
+            FPCoordinate::new(20, 21),
+            FPCoordinate::new(30, 29),
+            FPCoordinate::new(40, 41),
+        ];
+        let node_id_list = (0..coordinates.len()).collect_vec();
+        let axis = Axis::principal(&node_id_list, &coordinates);
+        assert!((axis.lat - axis.lon).abs() < 0.05);
+        assert!(axis.project(&coordinates[0]) < axis.project(&coordinates[4]));
+
+        let mut axes = Axes::with_directions(2);
+        axes.push(axis);
+        assert_eq!(axes.len(), 3);
+        assert_eq!(axes[2], axis);
+    }
+
     #[test]
     fn inertial_flow() {
         let edges = vec![
@@ -285,7 +750,15 @@ mod tests {
         ];
         let node_id_list = (0..coordinates.len()).collect_vec();
 
-        let result = sub_step(&edges, &node_id_list, &coordinates, 3, 0.25, upper_bound);
+        let axis = Axes::new()[3];
+        let result = sub_step(
+            &edges,
+            &node_id_list,
+            &coordinates,
+            &axis,
+            0.25,
+            upper_bound,
+        );
         assert_eq!(result.flow, 1);
         assert_eq!(result.balance, 0.5);
         assert_eq!(result.left_ids.len(), 3);
@@ -293,4 +766,189 @@ mod tests {
         assert_eq!(result.right_ids.len(), 3);
         assert_eq!(result.right_ids, vec![2, 0, 1]);
     }
+
+    #[test]
+    fn uneven_ratio() {
+        // a path of 12 nodes along the latitude axis
+        let edges = (0..11)
+            .flat_map(|u| {
+                [
+                    TrivialEdge {
+                        source: u,
+                        target: u + 1,
+                    },
+                    TrivialEdge {
+                        source: u + 1,
+                        target: u,
+                    },
+                ]
+            })
+            .collect_vec();
+        let coordinates = (0..12).map(|i| FPCoordinate::new(i, 0)).collect_vec();
+        let node_id_list = (0..12).collect_vec();
+        let axis = Axes::new()[0];
+
+        // two sources and four targets are contracted
+        let upper_bound = Arc::new(AtomicI32::new(12));
+        let result = sub_step_with_ratio(
+            &edges,
+            &node_id_list,
+            &coordinates,
+            &axis,
+            0.25,
+            1. / 3.,
+            upper_bound,
+        );
+        assert_eq!(result.flow, 1);
+        assert_eq!(result.left_ids, vec![0, 1]);
+        assert_eq!(result.right_ids.len(), 10);
+    }
+
+    #[test]
+    fn weighted_capacities() {
+        // a path of 12 nodes along the latitude axis with a weak link between 7 and 8
+        let edges = (0..11)
+            .flat_map(|u| {
+                let capacity = if u == 7 { 1 } else { 5 };
+                [
+                    InputEdge::new(u, u + 1, capacity),
+                    InputEdge::new(u + 1, u, capacity),
+                ]
+            })
+            .collect_vec();
+        let coordinates = (0..12).map(|i| FPCoordinate::new(i, 0)).collect_vec();
+        let node_id_list = (0..12).collect_vec();
+        let axis = Axes::new()[0];
+
+        let upper_bound = Arc::new(AtomicI32::new(i32::MAX));
+        let result = sub_step(
+            &edges,
+            &node_id_list,
+            &coordinates,
+            &axis,
+            0.25,
+            upper_bound,
+        );
+        assert_eq!(result.flow, 1);
+        assert_eq!(result.left_ids, (0..8).collect_vec());
+        assert_eq!(result.right_ids, (8..12).collect_vec());
+    }
+
+    #[test]
+    fn node_separator() {
+        // a 5x4 grid along the latitude axis, whose middle column separates it
+        let index = |row: usize, column: usize| 4 * row + column;
+        let mut edges = Vec::new();
+        for row in 0..5 {
+            for column in 0..4 {
+                let mut neighbors = Vec::new();
+                if row < 4 {
+                    neighbors.push(index(row + 1, column));
+                }
+                if column < 3 {
+                    neighbors.push(index(row, column + 1));
+                }
+                for neighbor in neighbors {
+                    edges.push(TrivialEdge {
+                        source: index(row, column),
+                        target: neighbor,
+                    });
+                    edges.push(TrivialEdge {
+                        source: neighbor,
+                        target: index(row, column),
+                    });
+                }
+            }
+        }
+        let coordinates = (0..20)
+            .map(|node: i32| FPCoordinate::new(node / 4, node % 4))
+            .collect_vec();
+        let node_id_list = (0..20).collect_vec();
+        let axis = Axes::new()[0];
+
+        let upper_bound = Arc::new(AtomicI32::new(i32::MAX));
+        let result = separator_sub_step(
+            &edges,
+            &node_id_list,
+            &coordinates,
+            &axis,
+            0.25,
+            upper_bound,
+        );
+        assert_eq!(result.flow, 4);
+        assert_eq!(result.separator_ids.len(), 4);
+        assert_eq!(result.left_ids.len() + result.right_ids.len(), 16);
+        for edge in &edges {
+            assert!(
+                !(result.left_ids.contains(&edge.source)
+                    && result.right_ids.contains(&edge.target))
+            );
+        }
+    }
+
+    #[test]
+    fn separator_from_cut() {
+        // a path 0-1-2-3 that is cut between 1 and 2
+        let edges = (0..3)
+            .flat_map(|u| {
+                [
+                    TrivialEdge {
+                        source: u,
+                        target: u + 1,
+                    },
+                    TrivialEdge {
+                        source: u + 1,
+                        target: u,
+                    },
+                ]
+            })
+            .collect_vec();
+        let cut = FlowResult {
+            flow: 1,
+            balance: 0.5,
+            left_ids: vec![0, 1],
+            right_ids: vec![2, 3],
+        };
+        let result = SeparatorResult::from_cut(&edges, &cut);
+        assert_eq!(result.flow, 1);
+        assert_eq!(result.separator_ids, vec![1]);
+        assert_eq!(result.left_ids, vec![0]);
+        assert_eq!(result.right_ids, vec![2, 3]);
+        assert_eq!(result.balance, 0.25);
+    }
+
+    #[test]
+    fn separator_from_cut_in_both_directions() {
+        // a directed cycle 0->1->2->3->0 that is cut between 1 and 2 and
+        // between 3 and 0
+        let edges = (0..4)
+            .map(|u| TrivialEdge {
+                source: u,
+                target: (u + 1) % 4,
+            })
+            .collect_vec();
+        let cut = FlowResult {
+            flow: 1,
+            balance: 0.5,
+            left_ids: vec![0, 1],
+            right_ids: vec![2, 3],
+        };
+        let result = SeparatorResult::from_cut(&edges, &cut);
+        assert_eq!(result.separator_ids.len(), 2);
+        let side = |node| {
+            if result.left_ids.contains(&node) {
+                Some(0)
+            } else if result.right_ids.contains(&node) {
+                Some(1)
+            } else {
+                None
+            }
+        };
+        for edge in &edges {
+            assert!(!matches!(
+                (side(edge.source), side(edge.target)),
+                (Some(a), Some(b)) if a != b
+            ));
+        }
+    }
 }
@@ -1,7 +1,9 @@
 use std::{
+    fmt::Display,
     fs::File,
     io::{self, BufRead, BufReader},
     path::Path,
+    str::FromStr,
 };
 
 use bincode::deserialize_from;
@@ -22,10 +24,75 @@ where
     Ok(io::BufReader::new(file).lines())
 }
 
-pub fn read_graph_into_trivial_edges(filename: &str) -> Vec<TrivialEdge> {
+/// An error while reading a text file, e.g. a malformed line of a graph file
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ParseError {
+    pub filename: String,
+    /// the offending line starting at 1, or 0 if the error concerns the whole file
+    pub line: usize,
+    pub reason: String,
+}
+
+impl ParseError {
+    pub fn new(filename: &str, line: usize, reason: impl Into<String>) -> Self {
+        Self {
+            filename: filename.to_string(),
+            line,
+            reason: reason.into(),
+        }
+    }
+}
+
+impl Display for ParseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.line == 0 {
+            write!(f, "{}: {}", self.filename, self.reason)
+        } else {
+            write!(f, "{}:{}: {}", self.filename, self.line, self.reason)
+        }
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+/// Returns an iterator over the lines of a text file that are numbered
+/// starting at 1. Errors carry the file name and the line number.
+pub fn numbered_lines(
+    filename: &str,
+) -> Result<impl Iterator<Item = Result<(usize, String), ParseError>> + '_, ParseError> {
+    let lines =
+        read_lines(filename).map_err(|error| ParseError::new(filename, 0, error.to_string()))?;
+    Ok(lines.enumerate().map(move |(index, line)| {
+        line.map(|line| (index + 1, line))
+            .map_err(|error| ParseError::new(filename, index + 1, error.to_string()))
+    }))
+}
+
+/// Parses the token at the given index of a tokenized line. The name of the
+/// field is used to describe missing or malformed tokens.
+pub fn parse_token<T: FromStr>(
+    filename: &str,
+    line: usize,
+    tokens: &[&str],
+    index: usize,
+    name: &str,
+) -> Result<T, ParseError> {
+    let token = tokens
+        .get(index)
+        .ok_or_else(|| ParseError::new(filename, line, format!("missing {name}")))?;
+    token
+        .parse()
+        .map_err(|_| ParseError::new(filename, line, format!("invalid {name} '{token}'")))
+}
+
+/// Reads the edges of a graph including their weights
+pub fn read_graph_into_weighted_edges(filename: &str) -> Vec<InputEdge<i32>> {
     let reader = BufReader::new(File::open(filename).unwrap());
+    deserialize_from(reader).unwrap()
+}
 
-    let input_edges: Vec<InputEdge<i32>> = deserialize_from(reader).unwrap();
+pub fn read_graph_into_trivial_edges(filename: &str) -> Vec<TrivialEdge> {
+    let input_edges = read_graph_into_weighted_edges(filename);
     let edges = input_edges
         .iter()
         .map(|edge| TrivialEdge {
@@ -41,3 +108,20 @@ pub fn read_coordinates(filename: &str) -> Vec<FPCoordinate> {
     let reader = BufReader::new(File::open(filename).unwrap());
     deserialize_from(reader).unwrap()
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::io::{parse_token, ParseError};
+
+    #[test]
+    fn parse_errors() {
+        let tokens = ["a", "12", "x"];
+        assert_eq!(parse_token::<usize>("g", 3, &tokens, 1, "node id"), Ok(12));
+        let error = parse_token::<usize>("g", 3, &tokens, 2, "node id").unwrap_err();
+        assert_eq!(error, ParseError::new("g", 3, "invalid node id 'x'"));
+        assert_eq!(error.to_string(), "g:3: invalid node id 'x'");
+        let error = parse_token::<i32>("g", 4, &tokens, 3, "weight").unwrap_err();
+        assert_eq!(error.to_string(), "g:4: missing weight");
+        assert_eq!(ParseError::new("g", 0, "empty").to_string(), "g: empty");
+    }
+}
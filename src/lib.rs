@@ -9,9 +9,14 @@ pub mod ddsg;
 pub mod dfs;
 pub mod dimacs;
 pub mod dinic;
+pub mod disjoint_paths;
 pub mod dynamic_graph;
 pub mod edge;
 pub mod edmonds_karp;
+pub mod flow_cutter;
+pub mod flow_decomposition;
+pub mod flow_verification;
+pub mod fm_refinement;
 pub mod ford_fulkerson;
 pub mod geometry;
 pub mod graph;
@@ -23,13 +28,22 @@ pub mod linked_list;
 pub mod lru;
 pub mod max_flow;
 pub mod metis;
+pub mod multi_level_partition;
+pub mod multilevel;
+pub mod nested_dissection;
+pub mod osm;
 pub mod partition;
+pub mod partition_metrics;
 pub mod projection;
 pub mod rdx_sort;
 pub mod renumbering_table;
+pub mod reordering;
 pub mod space_filling_curve;
+pub mod spectral;
 pub mod static_graph;
 pub mod tarjan;
+#[cfg(test)]
+mod test_utils;
 pub mod union_find;
 pub mod unsafe_slice;
 pub mod wgs84;
@@ -1,6 +1,12 @@
-use std::sync::{atomic::AtomicI32, Arc};
+use std::{
+    fmt::Display,
+    sync::{atomic::AtomicI32, Arc},
+};
 
-use crate::graph::NodeID;
+use crate::{
+    graph::{Graph, NodeID},
+    static_graph::StaticGraph,
+};
 use bitvec::vec::BitVec;
 
 #[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
@@ -20,9 +26,59 @@ impl From<i32> for ResidualCapacity {
     }
 }
 
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum MaxFlowError {
+    /// The max-flow computation was not run, yet
+    NotRun,
+    /// The computation was aborted as the flow exceeded the given upper bound
+    AbortedByUpperBound(i32),
+    /// The source is not a node of the graph
+    InvalidSource(NodeID),
+    /// The target is not a node of the graph or coincides with the source
+    InvalidTarget(NodeID),
+    /// The graph does not have any edges
+    EmptyGraph,
+}
+
+impl Display for MaxFlowError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            MaxFlowError::NotRun => write!(f, "max flow computation was not run"),
+            MaxFlowError::AbortedByUpperBound(bound) => {
+                write!(f, "max flow computation aborted by upper bound {bound}")
+            }
+            MaxFlowError::InvalidSource(source) => write!(f, "invalid source node {source}"),
+            MaxFlowError::InvalidTarget(target) => write!(f, "invalid target node {target}"),
+            MaxFlowError::EmptyGraph => write!(f, "graph has no edges"),
+        }
+    }
+}
+
+impl std::error::Error for MaxFlowError {}
+
+/// Checks that the residual graph is non-empty and that source and target are
+/// two distinct nodes of it.
+pub(crate) fn check_instance(
+    residual_graph: &StaticGraph<ResidualCapacity>,
+    source: NodeID,
+    target: NodeID,
+) -> Result<(), MaxFlowError> {
+    if residual_graph.number_of_edges() == 0 {
+        return Err(MaxFlowError::EmptyGraph);
+    }
+    if source >= residual_graph.number_of_nodes() {
+        return Err(MaxFlowError::InvalidSource(source));
+    }
+    if target >= residual_graph.number_of_nodes() || target == source {
+        return Err(MaxFlowError::InvalidTarget(target));
+    }
+    Ok(())
+}
+
 pub trait MaxFlow {
     fn run(&mut self);
     fn run_with_upper_bound(&mut self, bound: Arc<AtomicI32>);
-    fn max_flow(&self) -> Result<i32, String>;
-    fn assignment(&self, source: NodeID) -> Result<BitVec, String>;
+    fn max_flow(&self) -> Result<i32, MaxFlowError>;
+    fn assignment(&self, source: NodeID) -> Result<BitVec, MaxFlowError>;
+    fn residual_graph(&self) -> &StaticGraph<ResidualCapacity>;
 }
@@ -1,7 +1,18 @@
+use std::{
+    cmp::max,
+    fs::File,
+    io::{BufWriter, Write},
+};
+
 use itertools::Itertools;
 use log::info;
 
-use crate::{edge::InputEdge, geometry::primitives::FPCoordinate, graph::NodeID, io::read_lines};
+use crate::{
+    edge::InputEdge,
+    geometry::primitives::FPCoordinate,
+    graph::NodeID,
+    io::{numbered_lines, parse_token, ParseError},
+};
 
 pub enum WeightType {
     Unit,
@@ -32,30 +43,45 @@ impl TryFrom<i32> for Direction {
 pub fn read_graph<T: std::cmp::Eq + From<i32>>(
     filename: &str,
     _weight_type: WeightType,
-) -> Vec<InputEdge<T>> {
+) -> Result<Vec<InputEdge<T>>, ParseError> {
     let mut edges = Vec::new();
 
-    let mut lines = read_lines(filename).expect("could not load graph file");
+    let mut lines = numbered_lines(filename)?;
 
-    let first_line = lines.next().unwrap().unwrap();
-    let sizes = first_line
-        .get(..)
-        .unwrap_or("")
-        .split_ascii_whitespace()
-        .collect_vec();
-    let number_of_nodes = sizes[0].parse::<NodeID>().unwrap();
-    info!("expecting {} nodes and {} edges", number_of_nodes, sizes[1]);
+    let (number, first_line) = lines
+        .next()
+        .ok_or_else(|| ParseError::new(filename, 0, "missing header"))??;
+    let sizes = first_line.split_ascii_whitespace().collect_vec();
+    let number_of_nodes: NodeID = parse_token(filename, number, &sizes, 0, "number of nodes")?;
+    let number_of_edges: usize = parse_token(filename, number, &sizes, 1, "number of edges")?;
+    info!("expecting {number_of_nodes} nodes and {number_of_edges} edges");
 
     // load unweighted metis graph and coordinates
     for (source, line) in lines.enumerate() {
-        let line = line.unwrap();
-        let tokens = line.get(..).unwrap_or("").split_whitespace().collect_vec();
+        let (number, line) = line?;
+        let tokens = line.split_whitespace().collect_vec();
 
-        assert!(source < number_of_nodes);
+        if source >= number_of_nodes {
+            if tokens.is_empty() {
+                continue;
+            }
+            return Err(ParseError::new(
+                filename,
+                number,
+                format!("more adjacency lines than {number_of_nodes} nodes"),
+            ));
+        }
 
-        for token in tokens {
-            let target = token.parse::<NodeID>().unwrap() - 1;
-            assert!(target < number_of_nodes);
+        for index in 0..tokens.len() {
+            let target = parse_token::<NodeID>(filename, number, &tokens, index, "node id")?;
+            if !(1..=number_of_nodes).contains(&target) {
+                return Err(ParseError::new(
+                    filename,
+                    number,
+                    format!("node id {target} out of range 1..={number_of_nodes}"),
+                ));
+            }
+            let target = target - 1;
             // avoid eigenloops
             if source == target {
                 continue;
@@ -69,19 +95,185 @@ pub fn read_graph<T: std::cmp::Eq + From<i32>>(
         }
     }
     info!("loaded {} directed edges", edges.len());
-    edges
+    Ok(edges)
 }
 
-pub fn read_coordinates(filename: &str) -> Vec<FPCoordinate> {
+pub fn read_coordinates(filename: &str) -> Result<Vec<FPCoordinate>, ParseError> {
     let mut coordinates = Vec::new();
-    for line in read_lines(filename).expect("could not load coordinates file") {
-        let line = line.unwrap();
-        let tokens = line.get(..).unwrap_or("").split_whitespace().collect_vec();
-        let lon = tokens[0].parse::<f64>().unwrap() / 100_000.;
-        let lat = tokens[1].parse::<f64>().unwrap() / 100_000.;
+    for line in numbered_lines(filename)? {
+        let (number, line) = line?;
+        let tokens = line.split_whitespace().collect_vec();
+        if tokens.is_empty() {
+            continue;
+        }
+        // coordinates are given in units of 1e-5 degrees
+        let lon = parse_token::<f64>(filename, number, &tokens, 0, "longitude")?;
+        let lat = parse_token::<f64>(filename, number, &tokens, 1, "latitude")?;
         // let _z = tokens[2].parse::<f64>().unwrap();
-        coordinates.push(FPCoordinate::new_from_lat_lon(lat, lon));
+        coordinates.push(FPCoordinate::new(
+            (lat * 10.).round() as i32,
+            (lon * 10.).round() as i32,
+        ));
+    }
+
+    Ok(coordinates)
+}
+
+/// Writes a graph in METIS format, i.e. a header with the number of nodes and
+/// undirected edges followed by the adjacency list of each node. The format
+/// requires a symmetric graph without parallel edges, so each edge is written
+/// in both directions once, and self-loops and edge weights are dropped. The
+/// number of nodes is derived from the largest node id.
+pub fn write_graph<T: Eq>(filename: &str, edges: &[InputEdge<T>]) -> std::io::Result<()> {
+    let number_of_nodes = edges
+        .iter()
+        .map(|edge| max(edge.source, edge.target) + 1)
+        .max()
+        .unwrap_or(0);
+    let mut adjacency = vec![Vec::new(); number_of_nodes];
+    for edge in edges.iter().filter(|edge| edge.source != edge.target) {
+        adjacency[edge.source].push(edge.target + 1);
+        adjacency[edge.target].push(edge.source + 1);
+    }
+    for targets in &mut adjacency {
+        targets.sort_unstable();
+        targets.dedup();
+    }
+    let number_of_edges = adjacency.iter().map(Vec::len).sum::<usize>() / 2;
+
+    let mut file = BufWriter::new(File::create(filename)?);
+    writeln!(file, "{number_of_nodes} {number_of_edges}")?;
+    for targets in adjacency {
+        writeln!(file, "{}", targets.iter().join(" "))?;
     }
+    file.flush()
+}
 
-    coordinates
+/// Writes coordinates in units of 1e-5 degrees, i.e. the last digit of the
+/// fixed-point coordinates is rounded off.
+pub fn write_coordinates(filename: &str, coordinates: &[FPCoordinate]) -> std::io::Result<()> {
+    let mut file = BufWriter::new(File::create(filename)?);
+    for coordinate in coordinates {
+        writeln!(
+            file,
+            "{} {} 0",
+            (coordinate.lon as f64 / 10.).round(),
+            (coordinate.lat as f64 / 10.).round()
+        )?;
+    }
+    file.flush()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use crate::{
+        edge::InputEdge,
+        geometry::primitives::FPCoordinate,
+        io::ParseError,
+        metis::{read_coordinates, read_graph, write_coordinates, write_graph, WeightType},
+        test_utils::temp_file,
+    };
+
+    #[test]
+    fn adjacency_lines() {
+        // the third node has no neighbors
+        let path = temp_file("metis_adjacency_lines.graph");
+        fs::write(&path, "3 1\n2\n1\n\n").unwrap();
+        let edges = read_graph::<i32>(path.to_str().unwrap(), WeightType::Unit).unwrap();
+        fs::remove_file(&path).unwrap();
+        assert_eq!(
+            edges,
+            vec![InputEdge::new(0, 1, 1), InputEdge::new(1, 0, 1)]
+        );
+    }
+
+    #[test]
+    fn malformed_input() {
+        let path = temp_file("metis_malformed_input.graph");
+        let filename = path.to_str().unwrap();
+        for (content, line, reason) in [
+            ("", 0, "missing header"),
+            ("2 1\n3\n1\n", 2, "node id 3 out of range 1..=2"),
+            ("2 1\n2\n1 -1\n", 3, "invalid node id '-1'"),
+            ("2 1\n2\n1\n1\n", 4, "more adjacency lines than 2 nodes"),
+        ] {
+            fs::write(&path, content).unwrap();
+            let error = read_graph::<i32>(filename, WeightType::Unit).unwrap_err();
+            assert_eq!(error, ParseError::new(filename, line, reason));
+        }
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn round_trip() {
+        // the reader expects a symmetric graph
+        let edges = vec![
+            InputEdge::new(0, 1, 1),
+            InputEdge::new(0, 2, 1),
+            InputEdge::new(1, 0, 1),
+            InputEdge::new(2, 0, 1),
+            InputEdge::new(2, 3, 1),
+            InputEdge::new(3, 2, 1),
+        ];
+        let coordinates = vec![
+            FPCoordinate::new(48_137_150, 11_575_490),
+            FPCoordinate::new(40_712_780, -74_005_970),
+        ];
+        let (graph, co) = (
+            temp_file("metis_round_trip.graph"),
+            temp_file("metis_round_trip.xyz"),
+        );
+        write_graph(graph.to_str().unwrap(), &edges).unwrap();
+        write_coordinates(co.to_str().unwrap(), &coordinates).unwrap();
+        let header = fs::read_to_string(&graph).unwrap();
+        let reread = read_graph::<i32>(graph.to_str().unwrap(), WeightType::Unit);
+        let reread_coordinates = read_coordinates(co.to_str().unwrap());
+        fs::remove_file(&graph).unwrap();
+        fs::remove_file(&co).unwrap();
+
+        assert_eq!(header, "4 3\n2 3\n1\n1 4\n3\n");
+        assert_eq!(reread.unwrap(), edges);
+        assert_eq!(reread_coordinates.unwrap(), coordinates);
+    }
+
+    #[test]
+    fn asymmetric_graph() {
+        // parallel edges and edges in one direction only become single
+        // undirected edges, while self-loops are dropped
+        let edges = vec![
+            InputEdge::new(0, 1, 3),
+            InputEdge::new(0, 1, 5),
+            InputEdge::new(1, 0, 3),
+            InputEdge::new(2, 1, 1),
+            InputEdge::new(2, 2, 1),
+        ];
+        let path = temp_file("metis_asymmetric_graph.graph");
+        write_graph(path.to_str().unwrap(), &edges).unwrap();
+        let content = fs::read_to_string(&path).unwrap();
+        let reread = read_graph::<i32>(path.to_str().unwrap(), WeightType::Unit);
+        fs::remove_file(&path).unwrap();
+
+        assert_eq!(content, "3 2\n2\n1 3\n2\n");
+        assert_eq!(
+            reread.unwrap(),
+            vec![
+                InputEdge::new(0, 1, 1),
+                InputEdge::new(1, 0, 1),
+                InputEdge::new(1, 2, 1),
+                InputEdge::new(2, 1, 1),
+            ]
+        );
+    }
+
+    #[test]
+    fn coordinate_rounding() {
+        // 397e-5 degrees would be truncated to 3969e-6 degrees
+        let path = temp_file("metis_coordinate_rounding.xyz");
+        fs::write(&path, "397 785 0\n").unwrap();
+        let coordinates = read_coordinates(path.to_str().unwrap()).unwrap();
+        fs::remove_file(&path).unwrap();
+        assert_eq!(coordinates, vec![FPCoordinate::new(7850, 3970)]);
+    }
 }
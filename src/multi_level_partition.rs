@@ -0,0 +1,262 @@
+//! A container for hierarchical partitions. A `PartitionID` encodes the path
+//! of a node through the binary tree of bisections, which leaves the cells of
+//! each level implicit. This container materializes them once: for every
+//! level it stores the cell of each node, the relations to the cells of the
+//! adjacent levels, and the boundary nodes of each cell. Cells of a level are
+//! numbered consecutively in the order of their partition ids. Level 0 is the
+//! root that consists of a single cell.
+use std::{
+    collections::BTreeMap,
+    fs::File,
+    io::{BufReader, BufWriter},
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{edge::TrivialEdge, graph::NodeID, partition::PartitionID};
+
+pub type CellID = u32;
+
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct PartitionLevel {
+    /// the cell of each node
+    pub cells: Vec<CellID>,
+    /// the partition id of each cell
+    pub ids: Vec<PartitionID>,
+    /// the cell on the next coarser level of each cell, empty on level 0
+    pub parents: Vec<CellID>,
+    /// the cells on the next finer level, stored consecutively per cell
+    pub first_child: Vec<usize>,
+    pub children: Vec<CellID>,
+    /// the nodes with an edge to another cell, stored consecutively per cell
+    pub first_boundary_node: Vec<usize>,
+    pub boundary_nodes: Vec<NodeID>,
+}
+
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct MultiLevelPartition {
+    levels: Vec<PartitionLevel>,
+}
+
+/// Groups values by a key in 0..number_of_keys in a compressed layout of
+/// offsets and values, where values are stored in ascending order per key.
+fn group_by_key(number_of_keys: usize, mut pairs: Vec<(usize, usize)>) -> (Vec<usize>, Vec<usize>) {
+    pairs.sort_unstable();
+    let mut offsets = vec![0; number_of_keys + 1];
+    for (key, _) in &pairs {
+        offsets[key + 1] += 1;
+    }
+    for i in 0..number_of_keys {
+        offsets[i + 1] += offsets[i];
+    }
+    (offsets, pairs.into_iter().map(|(_, value)| value).collect())
+}
+
+impl MultiLevelPartition {
+    /// Builds the container from the partition id of each node. Nodes whose
+    /// ids end above the deepest level stay in their cell on all deeper levels.
+    ///
+    /// # Arguments
+    ///
+    /// * `edges` - a list of edges that represents the input graph
+    /// * `partition_ids` - the partition id of each node
+    pub fn new(edges: &[TrivialEdge], partition_ids: &[PartitionID]) -> Self {
+        let deepest_level = partition_ids.iter().map(|id| id.level()).max().unwrap_or(0);
+
+        let mut levels: Vec<PartitionLevel> = Vec::new();
+        let mut previous_index: BTreeMap<PartitionID, CellID> = BTreeMap::new();
+        for level in 0..=deepest_level {
+            let node_ids = partition_ids
+                .iter()
+                .map(|id| id.ancestor_at_level(level))
+                .collect::<Vec<_>>();
+
+            // number cells in the order of their ids
+            let mut index: BTreeMap<PartitionID, CellID> =
+                node_ids.iter().map(|id| (*id, 0)).collect();
+            for (cell, value) in index.values_mut().enumerate() {
+                *value = cell as CellID;
+            }
+            let ids = index.keys().copied().collect::<Vec<_>>();
+            let cells = node_ids.iter().map(|id| index[id]).collect::<Vec<_>>();
+
+            let parents = if level == 0 {
+                Vec::new()
+            } else {
+                ids.iter()
+                    .map(|id| previous_index[&id.ancestor_at_level(level - 1)])
+                    .collect()
+            };
+
+            let mut is_boundary = vec![false; partition_ids.len()];
+            for edge in edges {
+                if cells[edge.source] != cells[edge.target] {
+                    is_boundary[edge.source] = true;
+                    is_boundary[edge.target] = true;
+                }
+            }
+            let (first_boundary_node, boundary_nodes) = group_by_key(
+                ids.len(),
+                (0..partition_ids.len())
+                    .filter(|node| is_boundary[*node])
+                    .map(|node| (cells[node] as usize, node))
+                    .collect(),
+            );
+
+            levels.push(PartitionLevel {
+                cells,
+                ids,
+                parents,
+                first_child: Vec::new(),
+                children: Vec::new(),
+                first_boundary_node,
+                boundary_nodes,
+            });
+            previous_index = index;
+        }
+
+        // the children of a level are the inverse of the parents of the next one
+        for level in 0..levels.len() {
+            let (first_child, children) = match levels.get(level + 1) {
+                Some(next) => group_by_key(
+                    levels[level].ids.len(),
+                    next.parents
+                        .iter()
+                        .enumerate()
+                        .map(|(child, parent)| (*parent as usize, child))
+                        .collect(),
+                ),
+                None => (vec![0; levels[level].ids.len() + 1], Vec::new()),
+            };
+            levels[level].first_child = first_child;
+            levels[level].children = children.into_iter().map(|c| c as CellID).collect();
+        }
+
+        Self { levels }
+    }
+
+    /// Returns the number of levels including the root level 0
+    pub fn number_of_levels(&self) -> usize {
+        self.levels.len()
+    }
+
+    pub fn number_of_nodes(&self) -> usize {
+        self.levels.first().map_or(0, |level| level.cells.len())
+    }
+
+    pub fn level(&self, level: u8) -> &PartitionLevel {
+        &self.levels[level as usize]
+    }
+
+    pub fn number_of_cells(&self, level: u8) -> usize {
+        self.level(level).ids.len()
+    }
+
+    /// Returns the cell of a node on the given level
+    pub fn cell(&self, level: u8, node: NodeID) -> CellID {
+        self.level(level).cells[node]
+    }
+
+    pub fn partition_id(&self, level: u8, cell: CellID) -> PartitionID {
+        self.level(level).ids[cell as usize]
+    }
+
+    /// Returns the cell on the next coarser level that contains the given cell
+    pub fn parent(&self, level: u8, cell: CellID) -> Option<CellID> {
+        self.level(level).parents.get(cell as usize).copied()
+    }
+
+    /// Returns the cells on the next finer level that the given cell contains
+    pub fn children(&self, level: u8, cell: CellID) -> &[CellID] {
+        let level = self.level(level);
+        let cell = cell as usize;
+        &level.children[level.first_child[cell]..level.first_child[cell + 1]]
+    }
+
+    /// Returns the nodes of a cell that have an edge to another cell, ordered by id
+    pub fn boundary_nodes(&self, level: u8, cell: CellID) -> &[NodeID] {
+        let level = self.level(level);
+        let cell = cell as usize;
+        &level.boundary_nodes[level.first_boundary_node[cell]..level.first_boundary_node[cell + 1]]
+    }
+
+    pub fn write(&self, filename: &str) {
+        let writer = BufWriter::new(File::create(filename).unwrap());
+        bincode::serialize_into(writer, self).unwrap();
+    }
+
+    pub fn read(filename: &str) -> Self {
+        let reader = BufReader::new(File::open(filename).unwrap());
+        bincode::deserialize_from(reader).unwrap()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        edge::TrivialEdge, multi_level_partition::MultiLevelPartition, partition::PartitionID,
+        test_utils::temp_file,
+    };
+
+    // a path of six nodes 0-1-2-3-4-5
+    fn edges() -> Vec<TrivialEdge> {
+        [(0, 1), (1, 2), (2, 3), (3, 4), (4, 5)]
+            .iter()
+            .flat_map(|(u, v)| {
+                [
+                    TrivialEdge {
+                        source: *u,
+                        target: *v,
+                    },
+                    TrivialEdge {
+                        source: *v,
+                        target: *u,
+                    },
+                ]
+            })
+            .collect()
+    }
+
+    #[test]
+    fn cells_and_relations() {
+        // cells on level 1 are {0, 1, 2, 3} and {4, 5}, on level 2 {0, 1}, {2, 3} and {4, 5}
+        let partition_ids = [4, 4, 5, 5, 6, 6].map(PartitionID::new).to_vec();
+        let partition = MultiLevelPartition::new(&edges(), &partition_ids);
+        assert_eq!(partition.number_of_levels(), 3);
+        assert_eq!(partition.number_of_nodes(), 6);
+        assert_eq!(partition.number_of_cells(0), 1);
+        assert_eq!(partition.number_of_cells(1), 2);
+        assert_eq!(partition.number_of_cells(2), 3);
+
+        assert_eq!(partition.level(1).cells, vec![0, 0, 0, 0, 1, 1]);
+        assert_eq!(partition.level(2).cells, vec![0, 0, 1, 1, 2, 2]);
+        assert_eq!(partition.partition_id(2, 2), PartitionID::new(6));
+
+        assert_eq!(partition.parent(0, 0), None);
+        assert_eq!(partition.parent(1, 1), Some(0));
+        assert_eq!(partition.parent(2, 1), Some(0));
+        assert_eq!(partition.parent(2, 2), Some(1));
+        assert_eq!(partition.children(0, 0), &[0, 1]);
+        assert_eq!(partition.children(1, 0), &[0, 1]);
+        assert_eq!(partition.children(1, 1), &[2]);
+        assert!(partition.children(2, 0).is_empty());
+
+        assert!(partition.boundary_nodes(0, 0).is_empty());
+        assert_eq!(partition.boundary_nodes(1, 0), &[3]);
+        assert_eq!(partition.boundary_nodes(1, 1), &[4]);
+        assert_eq!(partition.boundary_nodes(2, 1), &[2, 3]);
+    }
+
+    #[test]
+    fn serialization_round_trip() {
+        let partition_ids = [2, 2, 2, 3, 3, 3].map(PartitionID::new).to_vec();
+        let partition = MultiLevelPartition::new(&edges(), &partition_ids);
+
+        let path = temp_file("multi_level_partition_round_trip.bin");
+        let filename = path.to_str().unwrap();
+        partition.write(filename);
+        let read = MultiLevelPartition::read(filename);
+        std::fs::remove_file(filename).unwrap();
+        assert_eq!(partition, read);
+    }
+}
@@ -0,0 +1,410 @@
+//! Multilevel graph bisection in the spirit of METIS. The graph is coarsened by
+//! contracting heavy-edge matchings until it is small, the coarsest graph is
+//! bisected by greedy graph growing, and the bisection is projected back
+//! through the levels while being refined by Fiduccia-Mattheyses on each of
+//! them. Other than inertial flow, no coordinates are needed.
+use std::collections::{BinaryHeap, VecDeque};
+
+use bitvec::vec::BitVec;
+use core::cmp::max;
+use itertools::Itertools;
+use log::debug;
+
+use crate::{
+    edge::{Edge, EdgeCapacity},
+    fm_refinement::refine_assignment,
+    graph::NodeID,
+    inertial_flow::FlowResult,
+    renumbering_table::RenumberingTable,
+};
+
+/// coarsening stops once a level has at most this many nodes
+const COARSEST_SIZE: usize = 100;
+/// number of seeds for the initial bisection by graph growing
+const INITIAL_TRIES: usize = 4;
+
+/// Weighted undirected graph of a single level of the hierarchy
+pub struct Level {
+    pub node_weights: Vec<usize>,
+    pub adjacency: Vec<Vec<(NodeID, usize)>>,
+}
+
+impl Level {
+    /// Builds a level from weighted edges. Edges are symmetrized, parallel
+    /// edges are merged by summing their weights, and eigenloops are removed.
+    pub fn new(
+        node_weights: Vec<usize>,
+        edges: impl Iterator<Item = (NodeID, NodeID, usize)>,
+    ) -> Self {
+        let mut arcs = edges
+            .filter(|(u, v, _)| u != v)
+            .flat_map(|(u, v, weight)| [(u, v, weight), (v, u, weight)])
+            .collect_vec();
+        arcs.sort_unstable();
+
+        let mut adjacency: Vec<Vec<(NodeID, usize)>> = vec![Vec::new(); node_weights.len()];
+        for (u, v, weight) in arcs {
+            match adjacency[u].last_mut() {
+                Some((last, accumulated)) if *last == v => *accumulated += weight,
+                _ => adjacency[u].push((v, weight)),
+            }
+        }
+        Self {
+            node_weights,
+            adjacency,
+        }
+    }
+
+    pub fn number_of_nodes(&self) -> usize {
+        self.node_weights.len()
+    }
+
+    pub fn total_weight(&self) -> usize {
+        self.node_weights.iter().sum()
+    }
+
+    /// Returns the accumulated weight of all edges that cross the bisection
+    pub fn cut_weight(&self, assignment: &BitVec) -> usize {
+        (0..self.number_of_nodes())
+            .filter(|u| assignment[*u])
+            .flat_map(|u| self.adjacency[u].iter())
+            .filter(|(v, _)| !assignment[*v])
+            .map(|(_, weight)| weight)
+            .sum()
+    }
+
+    /// Contracts a heavy-edge matching. Returns the coarser level and the
+    /// mapping of the nodes of this level to the nodes of the coarser one.
+    pub fn coarsen(&self) -> (Level, Vec<NodeID>) {
+        // keep coarse nodes light enough to allow for balanced bisections
+        let maximum_node_weight = max(2, 3 * self.total_weight() / (2 * COARSEST_SIZE));
+
+        // visiting nodes of small degree first leaves fewer of them unmatched
+        let mut order = (0..self.number_of_nodes()).collect_vec();
+        order.sort_by_key(|u| self.adjacency[*u].len());
+
+        let mut coarse_ids = vec![NodeID::MAX; self.number_of_nodes()];
+        let mut coarse_weights = Vec::new();
+        for u in order {
+            if coarse_ids[u] != NodeID::MAX {
+                continue;
+            }
+            let partner = self.adjacency[u]
+                .iter()
+                .filter(|(v, _)| {
+                    coarse_ids[*v] == NodeID::MAX
+                        && self.node_weights[u] + self.node_weights[*v] <= maximum_node_weight
+                })
+                .max_by_key(|(v, weight)| (*weight, std::cmp::Reverse(self.node_weights[*v])))
+                .map(|(v, _)| *v);
+
+            coarse_ids[u] = coarse_weights.len();
+            let mut weight = self.node_weights[u];
+            if let Some(v) = partner {
+                coarse_ids[v] = coarse_weights.len();
+                weight += self.node_weights[v];
+            }
+            coarse_weights.push(weight);
+        }
+
+        let edges = (0..self.number_of_nodes()).flat_map(|u| {
+            self.adjacency[u]
+                .iter()
+                // each undirected edge is added once, symmetrization restores it
+                .filter(move |(v, _)| u < *v)
+                .map(|(v, weight)| (coarse_ids[u], coarse_ids[*v], *weight))
+                .collect_vec()
+        });
+        (Level::new(coarse_weights, edges), coarse_ids)
+    }
+
+    /// Grows a region from the seed node until it holds the target weight.
+    /// Nodes are added by the largest decrease of the cut weight.
+    fn grow_bisection(&self, seed: NodeID, target_weight: usize) -> BitVec {
+        let mut region = BitVec::repeat(false, self.number_of_nodes());
+        let mut region_weight = 0;
+        // accumulated weight of the edges into the region
+        let mut connection = vec![0i64; self.number_of_nodes()];
+        let degree = |u: NodeID| -> i64 {
+            self.adjacency[u]
+                .iter()
+                .map(|(_, weight)| *weight as i64)
+                .sum()
+        };
+
+        let mut heap = BinaryHeap::new();
+        heap.push((-degree(seed), seed));
+        while region_weight < target_weight {
+            let node = match heap.pop() {
+                Some((gain, node)) => {
+                    if region[node] || gain != 2 * connection[node] - degree(node) {
+                        // outdated entry
+                        continue;
+                    }
+                    node
+                }
+                // the region is disconnected from the remaining nodes
+                None => match region.first_zero() {
+                    Some(node) => node,
+                    None => break,
+                },
+            };
+            region.set(node, true);
+            region_weight += self.node_weights[node];
+            for (v, weight) in &self.adjacency[node] {
+                if !region[*v] {
+                    connection[*v] += *weight as i64;
+                    heap.push((2 * connection[*v] - degree(*v), *v));
+                }
+            }
+        }
+        region
+    }
+
+    /// Computes an initial bisection by growing regions from several seeds
+    /// and picking the one with the smallest cut after refinement.
+    fn initial_bisection(&self, target_weight: usize, minimum_weights: [usize; 2]) -> BitVec {
+        let mut seeds = vec![self.peripheral_node()];
+        seeds.extend((1..INITIAL_TRIES).map(|i| i * self.number_of_nodes() / INITIAL_TRIES));
+        seeds.dedup();
+
+        seeds
+            .into_iter()
+            .map(|seed| {
+                let mut assignment = self.grow_bisection(seed, target_weight);
+                refine_assignment(self, &mut assignment, minimum_weights);
+                assignment
+            })
+            .min_by_key(|assignment| self.cut_weight(assignment))
+            .unwrap()
+    }
+
+    /// Returns the last node that is visited by a BFS from the first node
+    fn peripheral_node(&self) -> NodeID {
+        let mut visited: BitVec = BitVec::repeat(false, self.number_of_nodes());
+        let mut queue = VecDeque::from([0]);
+        visited.set(0, true);
+        let mut last = 0;
+        while let Some(u) = queue.pop_front() {
+            last = u;
+            for (v, _) in &self.adjacency[u] {
+                if !visited[*v] {
+                    visited.set(*v, true);
+                    queue.push_back(*v);
+                }
+            }
+        }
+        last
+    }
+}
+
+/// Bisects the graph induced by the node id list with the multilevel scheme.
+///
+/// # Arguments
+///
+/// * `input_edges` - a list of edges that represents the input graph
+/// * `node_id_list` - list of node ids
+/// * `balance_factor` - the minimum share of nodes on either side of the cut
+pub fn bisect<E: Edge<ID = NodeID> + EdgeCapacity>(
+    input_edges: &[E],
+    node_id_list: &[usize],
+    balance_factor: f64,
+) -> FlowResult {
+    bisect_with_ratio(input_edges, node_id_list, balance_factor, 0.5)
+}
+
+/// Bisects the graph induced by the node id list with the multilevel scheme
+/// into two sides of uneven size. The minimum size of each side is scaled by
+/// its share of the nodes.
+///
+/// # Arguments
+///
+/// * `input_edges` - a list of edges that represents the input graph
+/// * `node_id_list` - list of node ids
+/// * `balance_factor` - the minimum share of nodes on either side of the cut
+/// * `ratio` - the targeted share of nodes on the left side of the cut
+pub fn bisect_with_ratio<E: Edge<ID = NodeID> + EdgeCapacity>(
+    input_edges: &[E],
+    node_id_list: &[usize],
+    balance_factor: f64,
+    ratio: f64,
+) -> FlowResult {
+    debug_assert!(node_id_list.len() > 1);
+    debug_assert!(ratio > 0. && ratio < 1.);
+    let number_of_nodes = node_id_list.len();
+    let universe_size = input_edges.iter().fold(0, |acc, edge| {
+        max(acc, max(edge.source(), edge.target()) + 1)
+    });
+    let universe_size = node_id_list
+        .iter()
+        .fold(universe_size, |acc, id| max(acc, id + 1));
+
+    // nodes are numbered consecutively in the order of the node id list
+    let mut renumbering_table =
+        RenumberingTable::new_with_size_hint(universe_size, number_of_nodes);
+    for (local_id, id) in node_id_list.iter().enumerate() {
+        renumbering_table.set(*id, local_id);
+    }
+    let edges = input_edges
+        .iter()
+        .filter(|edge| {
+            renumbering_table.contains_key(edge.source())
+                && renumbering_table.contains_key(edge.target())
+        })
+        .map(|edge| {
+            (
+                renumbering_table.get(edge.source()),
+                renumbering_table.get(edge.target()),
+                usize::try_from(edge.capacity()).expect("negative edge capacity"),
+            )
+        })
+        .collect_vec();
+
+    // coarsen until the graph is small or the matchings stop shrinking it
+    let mut levels = vec![Level::new(vec![1; number_of_nodes], edges.iter().copied())];
+    let mut mappings = Vec::new();
+    while levels.last().unwrap().number_of_nodes() > COARSEST_SIZE {
+        let finer = levels.last().unwrap();
+        let (coarser, mapping) = finer.coarsen();
+        if 20 * coarser.number_of_nodes() > 19 * finer.number_of_nodes() {
+            break;
+        }
+        levels.push(coarser);
+        mappings.push(mapping);
+    }
+    debug!(
+        "coarsened {} nodes in {} levels to {} nodes",
+        number_of_nodes,
+        levels.len(),
+        levels.last().unwrap().number_of_nodes()
+    );
+
+    // the grown region is on the right side
+    let minimum_weight =
+        |share: f64| (balance_factor * 2. * share * number_of_nodes as f64).ceil() as usize;
+    let minimum_weights = [minimum_weight(ratio), minimum_weight(1. - ratio)];
+    let target_weight = ((1. - ratio) * number_of_nodes as f64) as usize;
+    let mut assignment = levels
+        .last()
+        .unwrap()
+        .initial_bisection(target_weight, minimum_weights);
+
+    // project the bisection to the finer levels and refine it on each of them
+    for (level, mapping) in levels.iter().rev().skip(1).zip(mappings.iter().rev()) {
+        assignment = mapping.iter().map(|coarse| assignment[*coarse]).collect();
+        refine_assignment(level, &mut assignment, minimum_weights);
+    }
+
+    let flow = edges
+        .iter()
+        .filter(|(u, v, _)| !assignment[*u] && assignment[*v])
+        .map(|(_, _, capacity)| *capacity as i32)
+        .sum();
+    let (right_ids, left_ids): (Vec<_>, Vec<_>) =
+        node_id_list
+            .iter()
+            .enumerate()
+            .partition_map(|(local_id, id)| {
+                if assignment[local_id] {
+                    itertools::Either::Left(*id)
+                } else {
+                    itertools::Either::Right(*id)
+                }
+            });
+    let balance = std::cmp::min(left_ids.len(), right_ids.len()) as f64 / number_of_nodes as f64;
+    debug!("multilevel cut: {flow}, balance: {balance}");
+
+    FlowResult {
+        flow,
+        balance,
+        left_ids,
+        right_ids,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bitvec::vec::BitVec;
+    use itertools::Itertools;
+
+    use crate::{
+        multilevel::{bisect, bisect_with_ratio, Level},
+        test_utils::{dumbbell, grid},
+    };
+
+    #[test]
+    fn coarsen_preserves_weights() {
+        let edges = dumbbell(10);
+        let level = Level::new(
+            vec![1; 200],
+            edges.iter().map(|edge| (edge.source, edge.target, 1)),
+        );
+        // each undirected edge is merged from its two directions
+        assert_eq!(level.adjacency.iter().map(|a| a.len()).sum::<usize>(), 722);
+
+        let (coarse, mapping) = level.coarsen();
+        assert!(coarse.number_of_nodes() < level.number_of_nodes());
+        assert_eq!(coarse.total_weight(), 200);
+        assert!(mapping.iter().all(|id| *id < coarse.number_of_nodes()));
+
+        // cut weights are identical on both levels for projected bisections
+        let coarse_assignment: BitVec = (0..coarse.number_of_nodes()).map(|u| u % 2 == 0).collect();
+        let assignment: BitVec = mapping.iter().map(|id| coarse_assignment[*id]).collect();
+        assert_eq!(
+            level.cut_weight(&assignment),
+            coarse.cut_weight(&coarse_assignment)
+        );
+    }
+
+    #[test]
+    fn grow_from_seed() {
+        let edges = dumbbell(6);
+        let level = Level::new(
+            vec![1; 72],
+            edges.iter().map(|edge| (edge.source, edge.target, 1)),
+        );
+        // the region grows within the second grid that holds the seed
+        let region = level.grow_bisection(50, 12);
+        assert!(region[50]);
+        assert_eq!(region.count_ones(), 12);
+        assert!(region.iter_ones().all(|node| (36..72).contains(&node)));
+    }
+
+    #[test]
+    fn dumbbell_bisection() {
+        let edges = dumbbell(12);
+        let node_id_list = (0..288).collect_vec();
+        let result = bisect(&edges, &node_id_list, 0.25);
+        assert_eq!(result.flow, 1);
+        assert_eq!(result.balance, 0.5);
+
+        let mut left_ids = result.left_ids.clone();
+        left_ids.sort();
+        assert!(left_ids == (0..144).collect_vec() || left_ids == (144..288).collect_vec());
+    }
+
+    #[test]
+    fn subset_of_nodes() {
+        // only the second grid is bisected
+        let edges = dumbbell(6);
+        let node_id_list = (36..72).collect_vec();
+        let result = bisect(&edges, &node_id_list, 0.25);
+        assert_eq!(result.left_ids.len() + result.right_ids.len(), 36);
+        assert!(result.balance >= 0.25);
+        assert_eq!(result.flow, 6);
+    }
+
+    #[test]
+    fn uneven_ratio() {
+        // a grid of 24 rows and 12 columns, where all cuts between rows are
+        // equally small
+        let edges = grid(12, 24);
+
+        let node_id_list = (0..288).collect_vec();
+        let result = bisect_with_ratio(&edges, &node_id_list, 0.25, 1. / 3.);
+        // each side keeps at least half of its targeted share
+        assert!(result.left_ids.len() >= 48);
+        assert!(result.right_ids.len() >= 96);
+        assert!(result.left_ids.len() < result.right_ids.len());
+    }
+}
@@ -0,0 +1,273 @@
+//! Nested dissection orderings. The graph is separated recursively by node
+//! separators, which are either computed directly or derived from edge cuts
+//! by covering the cut edges with nodes. Nodes are numbered such that both
+//! halves of a separator come before the separator itself, which keeps the
+//! fill-in of sparse matrix factorizations and the number of shortcuts of
+//! contraction hierarchies low. Any bisection algorithm can be plugged in,
+//! e.g. inertial flow, flow cutter or the multilevel partitioner.
+use std::ops::Range;
+
+use itertools::Itertools;
+
+use crate::{
+    edge::TrivialEdge,
+    graph::NodeID,
+    inertial_flow::{FlowResult, SeparatorResult},
+};
+
+/// A node of the separator tree. Leaves hold the nodes of a cell that was
+/// not bisected any further.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SeparatorNode {
+    pub separator: Vec<NodeID>,
+    /// indices of the subtrees of both halves into the tree
+    pub children: Vec<usize>,
+    /// the positions in the ordering that the subtree occupies
+    pub range: Range<usize>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct NestedDissection {
+    /// the nodes in elimination order
+    pub order: Vec<NodeID>,
+    /// the position of each node in the order
+    pub rank: Vec<usize>,
+    /// the separator tree, whose root is at index 0
+    pub tree: Vec<SeparatorNode>,
+}
+
+struct Dissector<'a, F> {
+    separate: &'a F,
+    minimum_cell_size: usize,
+    // the subproblem that each node currently belongs to
+    tags: Vec<usize>,
+    next_tag: usize,
+    order: Vec<NodeID>,
+    tree: Vec<SeparatorNode>,
+}
+
+impl<F> Dissector<'_, F>
+where
+    F: Fn(&[TrivialEdge], &[NodeID]) -> SeparatorResult,
+{
+    fn tag(&mut self, ids: &[NodeID]) -> usize {
+        self.next_tag += 1;
+        for id in ids {
+            self.tags[*id] = self.next_tag;
+        }
+        self.next_tag
+    }
+
+    /// Orders the nodes of a subproblem and returns the index of its subtree
+    fn dissect(&mut self, edges: &[TrivialEdge], ids: &[NodeID]) -> usize {
+        let tree_index = self.tree.len();
+        let start = self.order.len();
+        self.tree.push(SeparatorNode {
+            separator: Vec::new(),
+            children: Vec::new(),
+            range: start..start,
+        });
+
+        let result = if ids.len() > self.minimum_cell_size {
+            Some((self.separate)(edges, ids))
+        } else {
+            None
+        };
+        let Some(result) =
+            result.filter(|result| !result.left_ids.is_empty() && !result.right_ids.is_empty())
+        else {
+            // the cell is small enough or cannot be cut any further
+            self.order.extend_from_slice(ids);
+            self.tree[tree_index].separator = ids.to_vec();
+            self.tree[tree_index].range = start..self.order.len();
+            return tree_index;
+        };
+
+        // both halves are collected before recursing, as recursion retags nodes
+        let mut children = Vec::new();
+        for half in [result.left_ids, result.right_ids] {
+            let tag = self.tag(&half);
+            let half_edges = edges
+                .iter()
+                .filter(|edge| self.tags[edge.source] == tag && self.tags[edge.target] == tag)
+                .copied()
+                .collect_vec();
+            children.push((half_edges, half));
+        }
+        let children = children
+            .into_iter()
+            .map(|(half_edges, half)| self.dissect(&half_edges, &half))
+            .collect_vec();
+
+        self.order.extend_from_slice(&result.separator_ids);
+        let node = &mut self.tree[tree_index];
+        node.separator = result.separator_ids;
+        node.children = children;
+        node.range = start..self.order.len();
+        tree_index
+    }
+}
+
+/// Computes a nested dissection ordering by recursive bisection. The edges of
+/// each cut are covered greedily by nodes to obtain a separator. Cells of at
+/// most the minimum cell size are not bisected any further.
+///
+/// # Arguments
+///
+/// * `edges` - a list of edges that represents the input graph
+/// * `number_of_nodes` - the number of nodes of the input graph
+/// * `minimum_cell_size` - cells of this size are ordered without dissection
+/// * `bisect` - bisects the graph induced by a node id list
+pub fn nested_dissection<F>(
+    edges: &[TrivialEdge],
+    number_of_nodes: usize,
+    minimum_cell_size: usize,
+    bisect: &F,
+) -> NestedDissection
+where
+    F: Fn(&[TrivialEdge], &[NodeID]) -> FlowResult,
+{
+    nested_dissection_with_separators(edges, number_of_nodes, minimum_cell_size, &|edges, ids| {
+        SeparatorResult::from_cut(edges, &bisect(edges, ids))
+    })
+}
+
+/// Computes a nested dissection ordering from recursively computed node
+/// separators, e.g. by `inertial_flow::separator_sub_step`.
+///
+/// # Arguments
+///
+/// * `edges` - a list of edges that represents the input graph
+/// * `number_of_nodes` - the number of nodes of the input graph
+/// * `minimum_cell_size` - cells of this size are ordered without dissection
+/// * `separate` - separates the graph induced by a node id list
+pub fn nested_dissection_with_separators<F>(
+    edges: &[TrivialEdge],
+    number_of_nodes: usize,
+    minimum_cell_size: usize,
+    separate: &F,
+) -> NestedDissection
+where
+    F: Fn(&[TrivialEdge], &[NodeID]) -> SeparatorResult,
+{
+    let mut dissector = Dissector {
+        separate,
+        minimum_cell_size,
+        tags: vec![0; number_of_nodes],
+        next_tag: 0,
+        order: Vec::with_capacity(number_of_nodes),
+        tree: Vec::new(),
+    };
+    let ids = (0..number_of_nodes).collect_vec();
+    dissector.dissect(edges, &ids);
+
+    let order = dissector.order;
+    let mut rank = vec![0; number_of_nodes];
+    for (position, node) in order.iter().enumerate() {
+        rank[*node] = position;
+    }
+    NestedDissection {
+        order,
+        rank,
+        tree: dissector.tree,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use itertools::Itertools;
+
+    use std::sync::{atomic::AtomicI32, Arc};
+
+    use crate::{
+        inertial_flow::{separator_sub_step, Axes},
+        multilevel,
+        nested_dissection::{nested_dissection, nested_dissection_with_separators},
+        test_utils::{grid, grid_coordinates},
+    };
+
+    #[test]
+    fn grid_ordering() {
+        let edges = grid(16, 16);
+        let dissection = nested_dissection(&edges, 256, 16, &|edges, ids| {
+            multilevel::bisect(edges, ids, 0.25)
+        });
+
+        // the order is a permutation and the rank its inverse
+        assert_eq!(
+            dissection.order.iter().sorted().copied().collect_vec(),
+            (0..256).collect_vec()
+        );
+        for (position, node) in dissection.order.iter().enumerate() {
+            assert_eq!(dissection.rank[*node], position);
+        }
+
+        // separators come last within their subtree, and separate the children
+        let root = &dissection.tree[0];
+        assert_eq!(root.range, 0..256);
+        assert_eq!(root.children.len(), 2);
+        assert!(root.separator.len() <= 24);
+        for node in &root.separator {
+            assert!(dissection.rank[*node] >= 256 - root.separator.len());
+        }
+        let (left, right) = (
+            &dissection.tree[root.children[0]].range,
+            &dissection.tree[root.children[1]].range,
+        );
+        for edge in &edges {
+            let (u, v) = (dissection.rank[edge.source], dissection.rank[edge.target]);
+            assert!(!(left.contains(&u) && right.contains(&v)));
+        }
+    }
+
+    #[test]
+    fn small_graph_is_a_leaf() {
+        let edges = grid(3, 3);
+        let dissection = nested_dissection(&edges, 9, 16, &|edges, ids| {
+            multilevel::bisect(edges, ids, 0.25)
+        });
+        assert_eq!(dissection.tree.len(), 1);
+        assert_eq!(dissection.order, (0..9).collect_vec());
+        assert!(dissection.tree[0].children.is_empty());
+    }
+
+    #[test]
+    fn grid_ordering_with_node_separators() {
+        let edges = grid(16, 16);
+        let coordinates = grid_coordinates(16, 16);
+        let dissection = nested_dissection_with_separators(&edges, 256, 16, &|edges, ids| {
+            Axes::new()
+                .as_slice()
+                .iter()
+                .map(|axis| {
+                    separator_sub_step(
+                        edges,
+                        ids,
+                        &coordinates,
+                        axis,
+                        0.25,
+                        Arc::new(AtomicI32::new(i32::MAX)),
+                    )
+                })
+                .min_by_key(|result| result.flow)
+                .unwrap()
+        });
+
+        // no separator is larger than the straight line through the middle of the grid
+        let root = &dissection.tree[0];
+        assert!(root.separator.len() <= 16);
+        assert_eq!(root.children.len(), 2);
+        assert_eq!(
+            dissection.order.iter().sorted().copied().collect_vec(),
+            (0..256).collect_vec()
+        );
+        let (left, right) = (
+            &dissection.tree[root.children[0]].range,
+            &dissection.tree[root.children[1]].range,
+        );
+        for edge in &edges {
+            let (u, v) = (dissection.rank[edge.source], dissection.rank[edge.target]);
+            assert!(!(left.contains(&u) && right.contains(&v)));
+        }
+    }
+}
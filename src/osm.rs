@@ -0,0 +1,866 @@
+//! Import of road networks from OpenStreetMap files in XML or PBF format. Ways
+//! that are tagged as roads are split into segments between consecutive
+//! nodes, and each segment is exploded into directed edges according to its
+//! oneway tags. Edges are weighted by their great circle length in meters.
+//! Only the nodes that are part of a road are kept and numbered consecutively
+//! in the order in which they are first referenced.
+use std::{
+    collections::HashMap,
+    fs::File,
+    io::{BufReader, Read},
+};
+
+use flate2::read::ZlibDecoder;
+use log::{debug, info};
+use quick_xml::{
+    events::{BytesStart, Event},
+    Reader,
+};
+
+use crate::{
+    ddsg::Direction, edge::InputEdge, geometry::primitives::FPCoordinate, graph::NodeID,
+    great_circle::distance::haversine, io::ParseError,
+};
+
+/// the highway types that are imported as roads
+const ROAD_TYPES: [&str; 16] = [
+    "motorway",
+    "motorway_link",
+    "trunk",
+    "trunk_link",
+    "primary",
+    "primary_link",
+    "secondary",
+    "secondary_link",
+    "tertiary",
+    "tertiary_link",
+    "unclassified",
+    "residential",
+    "living_street",
+    "service",
+    "road",
+    "track",
+];
+
+/// the largest blob header and blob that the PBF format allows, which bound
+/// the memory that a malformed file can make the reader allocate
+const MAXIMUM_HEADER_SIZE: usize = 64 * 1024;
+const MAXIMUM_BLOB_SIZE: usize = 32 * 1024 * 1024;
+
+/// the key value pairs that are attached to a way
+type Tags = Vec<(String, String)>;
+
+/// A road network with edge lengths in meters
+pub struct RoadNetwork {
+    pub edges: Vec<InputEdge<i32>>,
+    pub coordinates: Vec<FPCoordinate>,
+}
+
+/// Returns the direction in which a way can be traversed, or None if the way
+/// is not a road.
+fn way_direction(tags: &[(String, String)]) -> Option<Direction> {
+    let tag = |key: &str| {
+        tags.iter()
+            .find(|(k, _)| k == key)
+            .map(|(_, value)| value.as_str())
+    };
+    let highway = tag("highway")?;
+    if !ROAD_TYPES.contains(&highway) || tag("area") == Some("yes") {
+        return None;
+    }
+    let direction = match tag("oneway") {
+        Some("yes" | "true" | "1") => Direction::Forward,
+        Some("-1" | "reverse") => Direction::Reverse,
+        Some("reversible" | "alternating") => Direction::Closed,
+        Some("no" | "false" | "0") => Direction::Both,
+        // motorways and roundabouts are implicitly oneway
+        _ if highway == "motorway" || tag("junction") == Some("roundabout") => Direction::Forward,
+        _ => Direction::Both,
+    };
+    Some(direction)
+}
+
+/// Collects nodes and roads of either file format and assembles the graph
+#[derive(Default)]
+struct Builder {
+    nodes: HashMap<i64, FPCoordinate>,
+    roads: Vec<(Vec<i64>, Direction)>,
+}
+
+impl Builder {
+    fn add_node(&mut self, id: i64, coordinate: FPCoordinate) {
+        self.nodes.insert(id, coordinate);
+    }
+
+    fn add_way(&mut self, refs: Vec<i64>, tags: &[(String, String)]) {
+        if let Some(direction) = way_direction(tags) {
+            self.roads.push((refs, direction));
+        }
+    }
+
+    fn build(self) -> RoadNetwork {
+        let mut ids = HashMap::new();
+        let mut coordinates = Vec::new();
+        let mut edges = Vec::new();
+        let mut missing_nodes = 0;
+        for (refs, direction) in &self.roads {
+            let mut previous: Option<(NodeID, FPCoordinate)> = None;
+            for osm_id in refs {
+                // ways of extracts may reference nodes outside of the extract
+                let Some(coordinate) = self.nodes.get(osm_id) else {
+                    missing_nodes += 1;
+                    previous = None;
+                    continue;
+                };
+                let id = *ids.entry(*osm_id).or_insert_with(|| {
+                    coordinates.push(*coordinate);
+                    coordinates.len() - 1
+                });
+                if let Some((previous_id, previous_coordinate)) = previous {
+                    if previous_id != id {
+                        let length = haversine(
+                            previous_coordinate.lat as f64 / 1000000.,
+                            previous_coordinate.lon as f64 / 1000000.,
+                            coordinate.lat as f64 / 1000000.,
+                            coordinate.lon as f64 / 1000000.,
+                        );
+                        // zero-length edges would make paths ambiguous
+                        let meters = std::cmp::max(1, (length * 1000.).round() as i32);
+                        direction.explode(previous_id, id, meters, &mut edges);
+                    }
+                }
+                previous = Some((id, *coordinate));
+            }
+        }
+        if missing_nodes > 0 {
+            debug!("{missing_nodes} node references could not be resolved");
+        }
+        info!(
+            "imported {} roads into {} nodes and {} directed edges",
+            self.roads.len(),
+            coordinates.len(),
+            edges.len()
+        );
+        RoadNetwork { edges, coordinates }
+    }
+}
+
+/// Converts a coordinate in degrees into a fixed-point coordinate
+fn from_degrees(lat: f64, lon: f64) -> FPCoordinate {
+    FPCoordinate::new(
+        (lat * 1000000.).round() as i32,
+        (lon * 1000000.).round() as i32,
+    )
+}
+
+/// Returns the attributes of an XML element as a map from key to value
+fn attributes(element: &BytesStart) -> Result<HashMap<String, String>, String> {
+    element
+        .attributes()
+        .map(|attribute| {
+            let attribute = attribute.map_err(|error| error.to_string())?;
+            let key = String::from_utf8_lossy(attribute.key.as_ref()).to_string();
+            let value = attribute
+                .unescape_value()
+                .map_err(|error| error.to_string())?
+                .to_string();
+            Ok((key, value))
+        })
+        .collect()
+}
+
+fn parse_attribute<T: std::str::FromStr>(
+    attributes: &HashMap<String, String>,
+    key: &str,
+) -> Result<T, String> {
+    let value = attributes
+        .get(key)
+        .ok_or_else(|| format!("missing attribute {key}"))?;
+    value
+        .parse()
+        .map_err(|_| format!("invalid attribute {key}='{value}'"))
+}
+
+/// Reads the road network of an OpenStreetMap XML file
+pub fn read_xml(filename: &str) -> Result<RoadNetwork, ParseError> {
+    let file =
+        File::open(filename).map_err(|error| ParseError::new(filename, 0, error.to_string()))?;
+    let mut reader = Reader::from_reader(BufReader::new(file));
+    let mut builder = Builder::default();
+
+    // the way that is currently being read
+    let mut way: Option<(Vec<i64>, Tags)> = None;
+    let mut buffer = Vec::new();
+    loop {
+        let error = |position: usize, reason: String| {
+            ParseError::new(filename, 0, format!("at byte {position}: {reason}"))
+        };
+        let event = reader
+            .read_event_into(&mut buffer)
+            .map_err(|e| error(reader.buffer_position(), e.to_string()))?;
+        let result = match &event {
+            Event::Start(element) | Event::Empty(element) => {
+                let is_empty = matches!(event, Event::Empty(_));
+                match element.name().as_ref() {
+                    b"node" => attributes(element).and_then(|attributes| {
+                        let id = parse_attribute(&attributes, "id")?;
+                        let lat = parse_attribute(&attributes, "lat")?;
+                        let lon = parse_attribute(&attributes, "lon")?;
+                        builder.add_node(id, from_degrees(lat, lon));
+                        Ok(())
+                    }),
+                    b"way" => {
+                        if !is_empty {
+                            way = Some((Vec::new(), Vec::new()));
+                        }
+                        Ok(())
+                    }
+                    b"nd" => attributes(element).and_then(|attributes| {
+                        if let Some((refs, _)) = &mut way {
+                            refs.push(parse_attribute(&attributes, "ref")?);
+                        }
+                        Ok(())
+                    }),
+                    b"tag" => attributes(element).and_then(|attributes| {
+                        if let Some((_, tags)) = &mut way {
+                            tags.push((
+                                parse_attribute(&attributes, "k")?,
+                                parse_attribute(&attributes, "v")?,
+                            ));
+                        }
+                        Ok(())
+                    }),
+                    _ => Ok(()),
+                }
+            }
+            Event::End(element) if element.name().as_ref() == b"way" => {
+                if let Some((refs, tags)) = way.take() {
+                    builder.add_way(refs, &tags);
+                }
+                Ok(())
+            }
+            Event::Eof => break,
+            _ => Ok(()),
+        };
+        result.map_err(|reason| error(reader.buffer_position(), reason))?;
+        buffer.clear();
+    }
+
+    Ok(builder.build())
+}
+
+/// A value of a protocol buffer field by its wire type
+enum Value<'a> {
+    Varint(u64),
+    Bytes(&'a [u8]),
+    Fixed,
+}
+
+/// Iterates over the fields of an encoded protocol buffer message
+struct Message<'a> {
+    data: &'a [u8],
+}
+
+impl<'a> Message<'a> {
+    fn new(data: &'a [u8]) -> Self {
+        Self { data }
+    }
+
+    fn varint(&mut self) -> Result<u64, String> {
+        let mut value = 0;
+        for shift in (0..64).step_by(7) {
+            let (byte, rest) = self.data.split_first().ok_or("truncated varint")?;
+            self.data = rest;
+            value |= ((byte & 0x7f) as u64) << shift;
+            if byte & 0x80 == 0 {
+                return Ok(value);
+            }
+        }
+        Err("overlong varint".to_string())
+    }
+
+    fn bytes(&mut self, length: usize) -> Result<&'a [u8], String> {
+        if length > self.data.len() {
+            return Err("truncated field".to_string());
+        }
+        let (bytes, rest) = self.data.split_at(length);
+        self.data = rest;
+        Ok(bytes)
+    }
+
+    fn field(&mut self) -> Result<(u64, Value<'a>), String> {
+        let key = self.varint()?;
+        let value = match key & 7 {
+            0 => Value::Varint(self.varint()?),
+            1 => {
+                self.bytes(8)?;
+                Value::Fixed
+            }
+            2 => {
+                let length = self.varint()? as usize;
+                Value::Bytes(self.bytes(length)?)
+            }
+            5 => {
+                self.bytes(4)?;
+                Value::Fixed
+            }
+            wire_type => return Err(format!("unsupported wire type {wire_type}")),
+        };
+        Ok((key >> 3, value))
+    }
+}
+
+impl<'a> Iterator for Message<'a> {
+    type Item = Result<(u64, Value<'a>), String>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.data.is_empty() {
+            None
+        } else {
+            Some(self.field())
+        }
+    }
+}
+
+fn zigzag(value: u64) -> i64 {
+    (value >> 1) as i64 ^ -((value & 1) as i64)
+}
+
+/// Decodes a packed repeated field, which may also be a single unpacked value
+fn packed(value: &Value) -> Result<Vec<u64>, String> {
+    match value {
+        Value::Varint(value) => Ok(vec![*value]),
+        Value::Bytes(data) => {
+            let mut message = Message::new(data);
+            let mut values = Vec::new();
+            while !message.data.is_empty() {
+                values.push(message.varint()?);
+            }
+            Ok(values)
+        }
+        Value::Fixed => Err("expected a varint field".to_string()),
+    }
+}
+
+/// Decodes a packed field of delta-encoded signed integers
+fn delta_decoded(value: &Value) -> Result<Vec<i64>, String> {
+    let mut accumulated = 0;
+    Ok(packed(value)?
+        .into_iter()
+        .map(|delta| {
+            accumulated += zigzag(delta);
+            accumulated
+        })
+        .collect())
+}
+
+fn bytes<'a>(value: &Value<'a>) -> Result<&'a [u8], String> {
+    match value {
+        Value::Bytes(data) => Ok(data),
+        _ => Err("expected a length-delimited field".to_string()),
+    }
+}
+
+fn varint(value: &Value) -> Result<u64, String> {
+    match value {
+        Value::Varint(value) => Ok(*value),
+        _ => Err("expected a varint field".to_string()),
+    }
+}
+
+/// The parameters that convert the encoded coordinates of a block
+struct Granularity {
+    granularity: i64,
+    lat_offset: i64,
+    lon_offset: i64,
+}
+
+impl Granularity {
+    /// Converts the encoded coordinates into units of 1e-6 degrees
+    fn coordinate(&self, lat: i64, lon: i64) -> FPCoordinate {
+        let convert = |offset: i64, value: i64| {
+            ((offset + self.granularity * value) as f64 / 1000.).round() as i32
+        };
+        FPCoordinate::new(convert(self.lat_offset, lat), convert(self.lon_offset, lon))
+    }
+}
+
+fn decode_dense_nodes(
+    data: &[u8],
+    granularity: &Granularity,
+    builder: &mut Builder,
+) -> Result<(), String> {
+    let (mut ids, mut lats, mut lons) = (Vec::new(), Vec::new(), Vec::new());
+    for field in Message::new(data) {
+        match field? {
+            (1, value) => ids = delta_decoded(&value)?,
+            (8, value) => lats = delta_decoded(&value)?,
+            (9, value) => lons = delta_decoded(&value)?,
+            _ => {}
+        }
+    }
+    if ids.len() != lats.len() || ids.len() != lons.len() {
+        return Err("dense nodes of inconsistent lengths".to_string());
+    }
+    for ((id, lat), lon) in ids.into_iter().zip(lats).zip(lons) {
+        builder.add_node(id, granularity.coordinate(lat, lon));
+    }
+    Ok(())
+}
+
+fn decode_node(
+    data: &[u8],
+    granularity: &Granularity,
+    builder: &mut Builder,
+) -> Result<(), String> {
+    let (mut id, mut lat, mut lon) = (0, 0, 0);
+    for field in Message::new(data) {
+        match field? {
+            (1, value) => id = zigzag(varint(&value)?),
+            (8, value) => lat = zigzag(varint(&value)?),
+            (9, value) => lon = zigzag(varint(&value)?),
+            _ => {}
+        }
+    }
+    builder.add_node(id, granularity.coordinate(lat, lon));
+    Ok(())
+}
+
+fn decode_way(data: &[u8], strings: &[String], builder: &mut Builder) -> Result<(), String> {
+    let (mut keys, mut values, mut refs) = (Vec::new(), Vec::new(), Vec::new());
+    for field in Message::new(data) {
+        match field? {
+            (2, value) => keys = packed(&value)?,
+            (3, value) => values = packed(&value)?,
+            (8, value) => refs = delta_decoded(&value)?,
+            _ => {}
+        }
+    }
+    let string = |index: u64| {
+        strings
+            .get(index as usize)
+            .cloned()
+            .ok_or_else(|| format!("string index {index} out of range"))
+    };
+    let tags = keys
+        .into_iter()
+        .zip(values)
+        .map(|(key, value)| Ok((string(key)?, string(value)?)))
+        .collect::<Result<Tags, String>>()?;
+    builder.add_way(refs, &tags);
+    Ok(())
+}
+
+fn decode_primitive_block(data: &[u8], builder: &mut Builder) -> Result<(), String> {
+    let mut strings = Vec::new();
+    let mut groups = Vec::new();
+    let mut granularity = Granularity {
+        granularity: 100,
+        lat_offset: 0,
+        lon_offset: 0,
+    };
+    for field in Message::new(data) {
+        match field? {
+            (1, value) => {
+                for field in Message::new(bytes(&value)?) {
+                    if let (1, value) = field? {
+                        strings.push(String::from_utf8_lossy(bytes(&value)?).to_string());
+                    }
+                }
+            }
+            (2, value) => groups.push(bytes(&value)?),
+            (17, value) => granularity.granularity = varint(&value)? as i64,
+            (19, value) => granularity.lat_offset = varint(&value)? as i64,
+            (20, value) => granularity.lon_offset = varint(&value)? as i64,
+            _ => {}
+        }
+    }
+
+    // the parameters of the block may follow its groups
+    for group in groups {
+        for field in Message::new(group) {
+            match field? {
+                (1, value) => decode_node(bytes(&value)?, &granularity, builder)?,
+                (2, value) => decode_dense_nodes(bytes(&value)?, &granularity, builder)?,
+                (3, value) => decode_way(bytes(&value)?, &strings, builder)?,
+                _ => {}
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Checks that the file does not need features beyond nodes and ways
+fn check_header_block(data: &[u8]) -> Result<(), String> {
+    for field in Message::new(data) {
+        if let (4, value) = field? {
+            let feature = String::from_utf8_lossy(bytes(&value)?).to_string();
+            if feature != "OsmSchema-V0.6" && feature != "DenseNodes" {
+                return Err(format!("unsupported required feature {feature}"));
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Returns the uncompressed content of a blob
+fn decode_blob(data: &[u8]) -> Result<Vec<u8>, String> {
+    let mut raw_size = 0;
+    for field in Message::new(data) {
+        match field? {
+            (1, value) => return Ok(bytes(&value)?.to_vec()),
+            (2, value) => {
+                raw_size = varint(&value)? as usize;
+                if raw_size > MAXIMUM_BLOB_SIZE {
+                    return Err(format!(
+                        "raw size of {raw_size} bytes exceeds the limit of {MAXIMUM_BLOB_SIZE}"
+                    ));
+                }
+            }
+            (3, value) => {
+                // the raw size is only a hint, so the output is bounded as well
+                let mut content = Vec::with_capacity(raw_size);
+                ZlibDecoder::new(bytes(&value)?)
+                    .take(MAXIMUM_BLOB_SIZE as u64 + 1)
+                    .read_to_end(&mut content)
+                    .map_err(|error| error.to_string())?;
+                if content.len() > MAXIMUM_BLOB_SIZE {
+                    return Err(format!(
+                        "decompressed blob exceeds the limit of {MAXIMUM_BLOB_SIZE} bytes"
+                    ));
+                }
+                return Ok(content);
+            }
+            (number, _) if number <= 7 => {
+                return Err(format!("unsupported blob compression {number}"))
+            }
+            _ => {}
+        }
+    }
+    Err("blob without data".to_string())
+}
+
+/// Reads the road network of an OpenStreetMap PBF file
+pub fn read_pbf(filename: &str) -> Result<RoadNetwork, ParseError> {
+    let mut file = BufReader::new(
+        File::open(filename).map_err(|error| ParseError::new(filename, 0, error.to_string()))?,
+    );
+    let mut builder = Builder::default();
+    let mut blob_count = 0;
+    loop {
+        let error = |reason: String| {
+            ParseError::new(filename, 0, format!("in blob {blob_count}: {reason}"))
+        };
+        // each blob is preceded by its header and the big-endian size of it
+        let mut header_size = [0; 4];
+        match file.read_exact(&mut header_size) {
+            Ok(()) => {}
+            Err(e) if e.kind() == std::io::ErrorKind::UnexpectedEof => break,
+            Err(e) => return Err(error(e.to_string())),
+        }
+        let header_size = u32::from_be_bytes(header_size) as usize;
+        if header_size > MAXIMUM_HEADER_SIZE {
+            return Err(error(format!(
+                "header of {header_size} bytes exceeds the limit of {MAXIMUM_HEADER_SIZE}"
+            )));
+        }
+        let mut header = vec![0; header_size];
+        file.read_exact(&mut header)
+            .map_err(|e| error(format!("truncated header: {e}")))?;
+
+        let (mut blob_type, mut blob_size) = (String::new(), 0);
+        for field in Message::new(&header) {
+            match field.map_err(error)? {
+                (1, value) => {
+                    blob_type = String::from_utf8_lossy(bytes(&value).map_err(error)?).to_string()
+                }
+                (3, value) => blob_size = varint(&value).map_err(error)? as usize,
+                _ => {}
+            }
+        }
+        if blob_size > MAXIMUM_BLOB_SIZE {
+            return Err(error(format!(
+                "blob of {blob_size} bytes exceeds the limit of {MAXIMUM_BLOB_SIZE}"
+            )));
+        }
+        let mut blob = vec![0; blob_size];
+        file.read_exact(&mut blob)
+            .map_err(|e| error(format!("truncated blob: {e}")))?;
+
+        let content = decode_blob(&blob).map_err(error)?;
+        match blob_type.as_str() {
+            "OSMHeader" => check_header_block(&content).map_err(error)?,
+            "OSMData" => decode_primitive_block(&content, &mut builder).map_err(error)?,
+            _ => debug!("skipping blob of unknown type {blob_type}"),
+        }
+        blob_count += 1;
+    }
+
+    Ok(builder.build())
+}
+
+/// Reads the road network of an OpenStreetMap file. Files whose name ends in
+/// `.pbf` are read as PBF, all others as XML.
+pub fn read_file(filename: &str) -> Result<RoadNetwork, ParseError> {
+    if filename.ends_with(".pbf") {
+        read_pbf(filename)
+    } else {
+        read_xml(filename)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{fs, io::Write};
+
+    use flate2::{write::ZlibEncoder, Compression};
+    use itertools::Itertools;
+
+    use crate::{
+        geometry::primitives::FPCoordinate,
+        io::ParseError,
+        osm::{read_file, read_pbf, read_xml},
+        test_utils::temp_file,
+    };
+
+    fn varint(mut value: u64, buffer: &mut Vec<u8>) {
+        while value >= 0x80 {
+            buffer.push(value as u8 | 0x80);
+            value >>= 7;
+        }
+        buffer.push(value as u8);
+    }
+
+    fn zigzag(value: i64) -> u64 {
+        ((value << 1) ^ (value >> 63)) as u64
+    }
+
+    fn varint_field(number: u64, value: u64, buffer: &mut Vec<u8>) {
+        varint(number << 3, buffer);
+        varint(value, buffer);
+    }
+
+    fn bytes_field(number: u64, data: &[u8], buffer: &mut Vec<u8>) {
+        varint(number << 3 | 2, buffer);
+        varint(data.len() as u64, buffer);
+        buffer.extend_from_slice(data);
+    }
+
+    fn packed_field(number: u64, values: &[u64], buffer: &mut Vec<u8>) {
+        let mut data = Vec::new();
+        values.iter().for_each(|value| varint(*value, &mut data));
+        bytes_field(number, &data, buffer);
+    }
+
+    fn delta_field(number: u64, values: &[i64], buffer: &mut Vec<u8>) {
+        let deltas = [0]
+            .iter()
+            .chain(values)
+            .tuple_windows()
+            .map(|(a, b)| zigzag(b - a))
+            .collect_vec();
+        packed_field(number, &deltas, buffer);
+    }
+
+    fn blob(blob_type: &str, content: &[u8], file: &mut Vec<u8>) {
+        let mut encoder = ZlibEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(content).unwrap();
+        let mut blob = Vec::new();
+        varint_field(2, content.len() as u64, &mut blob);
+        bytes_field(3, &encoder.finish().unwrap(), &mut blob);
+
+        let mut header = Vec::new();
+        bytes_field(1, blob_type.as_bytes(), &mut header);
+        varint_field(3, blob.len() as u64, &mut header);
+        file.extend_from_slice(&(header.len() as u32).to_be_bytes());
+        file.extend_from_slice(&header);
+        file.extend_from_slice(&blob);
+    }
+
+    // four nodes along the equator that are 0.001 degrees apart
+    const XML: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<osm version="0.6">
+  <node id="10" lat="0.0" lon="0.0"/>
+  <node id="11" lat="0.0" lon="0.001"/>
+  <node id="12" lat="0.0" lon="0.002"/>
+  <node id="13" lat="0.0" lon="0.003">
+    <tag k="highway" v="traffic_signals"/>
+  </node>
+  <way id="1">
+    <nd ref="10"/>
+    <nd ref="11"/>
+    <tag k="highway" v="residential"/>
+  </way>
+  <way id="2">
+    <nd ref="11"/>
+    <nd ref="12"/>
+    <tag k="highway" v="primary"/>
+    <tag k="oneway" v="yes"/>
+  </way>
+  <way id="3">
+    <nd ref="13"/>
+    <nd ref="12"/>
+    <tag k="highway" v="tertiary"/>
+    <tag k="oneway" v="-1"/>
+  </way>
+  <way id="4">
+    <nd ref="10"/>
+    <nd ref="13"/>
+    <tag k="highway" v="footway"/>
+  </way>
+  <way id="5">
+    <nd ref="12"/>
+    <nd ref="99"/>
+    <tag k="highway" v="service"/>
+    <tag k="junction" v="roundabout"/>
+  </way>
+</osm>
+"#;
+
+    #[test]
+    fn xml_roads() {
+        let path = temp_file("osm_roads.osm");
+        fs::write(&path, XML).unwrap();
+        let network = read_xml(path.to_str().unwrap()).unwrap();
+        fs::remove_file(&path).unwrap();
+
+        // the footway is skipped and the reference to the missing node 99 is
+        // dropped, the reverse oneway is traversed against its node order
+        assert_eq!(
+            network.coordinates,
+            [0, 1000, 2000, 3000]
+                .map(|lon| FPCoordinate::new(0, lon))
+                .to_vec()
+        );
+        let edges = network
+            .edges
+            .iter()
+            .map(|edge| (edge.source, edge.target, edge.data))
+            .collect_vec();
+        assert_eq!(
+            edges,
+            vec![(0, 1, 111), (1, 0, 111), (1, 2, 111), (2, 3, 111)]
+        );
+    }
+
+    #[test]
+    fn pbf_roads() {
+        let mut header_block = Vec::new();
+        bytes_field(4, b"OsmSchema-V0.6", &mut header_block);
+        bytes_field(4, b"DenseNodes", &mut header_block);
+
+        // the same roads as in the XML test with a coarser granularity
+        let mut dense = Vec::new();
+        delta_field(1, &[10, 11, 12, 13], &mut dense);
+        delta_field(8, &[0, 0, 0, 0], &mut dense);
+        delta_field(9, &[0, 1000, 2000, 3000], &mut dense);
+        let mut group = Vec::new();
+        bytes_field(2, &dense, &mut group);
+        let strings = ["", "highway", "residential", "oneway", "-1", "footway"];
+        for (refs, keys, values) in [
+            (vec![10, 11], vec![1], vec![2]),
+            (vec![12, 11], vec![1, 3], vec![2, 4]),
+            (vec![10, 13], vec![1], vec![5]),
+        ] {
+            let mut way = Vec::new();
+            packed_field(2, &keys, &mut way);
+            packed_field(3, &values, &mut way);
+            delta_field(8, &refs, &mut way);
+            bytes_field(3, &way, &mut group);
+        }
+        let mut string_table = Vec::new();
+        strings
+            .iter()
+            .for_each(|string| bytes_field(1, string.as_bytes(), &mut string_table));
+        let mut block = Vec::new();
+        bytes_field(1, &string_table, &mut block);
+        bytes_field(2, &group, &mut block);
+        varint_field(17, 1000, &mut block);
+
+        let mut file = Vec::new();
+        blob("OSMHeader", &header_block, &mut file);
+        blob("OSMData", &block, &mut file);
+        let path = temp_file("osm_roads.osm.pbf");
+        fs::write(&path, &file).unwrap();
+        let network = read_file(path.to_str().unwrap()).unwrap();
+
+        assert_eq!(
+            network.coordinates,
+            [0, 1000, 2000]
+                .map(|lon| FPCoordinate::new(0, lon))
+                .to_vec()
+        );
+        let edges = network
+            .edges
+            .iter()
+            .map(|edge| (edge.source, edge.target, edge.data))
+            .collect_vec();
+        assert_eq!(edges, vec![(0, 1, 111), (1, 0, 111), (1, 2, 111)]);
+
+        // a required feature that is not supported is rejected
+        let mut header_block = Vec::new();
+        bytes_field(4, b"HistoricalInformation", &mut header_block);
+        let mut file = Vec::new();
+        blob("OSMHeader", &header_block, &mut file);
+        fs::write(&path, &file).unwrap();
+        let error = read_pbf(path.to_str().unwrap()).err().unwrap();
+        fs::remove_file(&path).unwrap();
+        assert!(error
+            .to_string()
+            .ends_with("in blob 0: unsupported required feature HistoricalInformation"));
+    }
+
+    #[test]
+    fn pbf_limits() {
+        let path = temp_file("osm_limits.osm.pbf");
+        let filename = path.to_str().unwrap();
+        let mut header_block = Vec::new();
+        bytes_field(4, b"OsmSchema-V0.6", &mut header_block);
+        let mut valid = Vec::new();
+        blob("OSMHeader", &header_block, &mut valid);
+
+        // sizes beyond the limits are rejected before anything is allocated
+        let mut oversized_header = valid.clone();
+        oversized_header.extend_from_slice(&(64 * 1024 + 1u32).to_be_bytes());
+        let mut oversized_blob = Vec::new();
+        bytes_field(1, b"OSMData", &mut oversized_blob);
+        varint_field(3, 32 * 1024 * 1024 + 1, &mut oversized_blob);
+        let mut oversized_blob_file = valid.clone();
+        oversized_blob_file.extend_from_slice(&(oversized_blob.len() as u32).to_be_bytes());
+        oversized_blob_file.extend_from_slice(&oversized_blob);
+        let mut oversized_raw_size = Vec::new();
+        varint_field(2, 32 * 1024 * 1024 + 1, &mut oversized_raw_size);
+        bytes_field(1, &[], &mut oversized_raw_size);
+        let mut raw_size_header = Vec::new();
+        bytes_field(1, b"OSMData", &mut raw_size_header);
+        varint_field(3, oversized_raw_size.len() as u64, &mut raw_size_header);
+        let mut oversized_raw_size_file = valid.clone();
+        oversized_raw_size_file.extend_from_slice(&(raw_size_header.len() as u32).to_be_bytes());
+        oversized_raw_size_file.extend_from_slice(&raw_size_header);
+        oversized_raw_size_file.extend_from_slice(&oversized_raw_size);
+
+        for (content, reason) in [
+            (
+                oversized_header,
+                "in blob 1: header of 65537 bytes exceeds the limit of 65536",
+            ),
+            (
+                oversized_blob_file,
+                "in blob 1: blob of 33554433 bytes exceeds the limit of 33554432",
+            ),
+            (
+                oversized_raw_size_file,
+                "in blob 1: raw size of 33554433 bytes exceeds the limit of 33554432",
+            ),
+            (
+                valid[..valid.len() - 1].to_vec(),
+                "in blob 0: truncated blob: failed to fill whole buffer",
+            ),
+        ] {
+            fs::write(&path, content).unwrap();
+            let error = read_pbf(filename).err().unwrap();
+            assert_eq!(error, ParseError::new(filename, 0, reason));
+        }
+        fs::remove_file(&path).unwrap();
+    }
+}
@@ -1,53 +1,92 @@
-use core::cmp::max;
+use core::cmp::{max, min};
+use itertools::Itertools;
+use log::{debug, warn};
+use num::PrimInt;
+use rayon::prelude::*;
 use serde::{Deserialize, Serialize};
-use std::{fmt::Display, hash::Hash};
+use std::{
+    collections::HashSet,
+    fmt::Display,
+    hash::Hash,
+    ops::RangeInclusive,
+    sync::{
+        atomic::{AtomicI32, AtomicUsize, Ordering},
+        Arc, Mutex,
+    },
+};
+
+use crate::{
+    bin_pack::bin_pack_assignment,
+    edge::{Edge, EdgeCapacity, TrivialEdge},
+    flow_cutter, fm_refinement,
+    geometry::primitives::FPCoordinate,
+    graph::NodeID,
+    inertial_flow::{self, flow_cmp, separator_cmp, Axes, Axis, FlowResult, SeparatorResult},
+    multilevel,
+    renumbering_table::RenumberingTable,
+    spectral,
+    union_find::UnionFind,
+    unsafe_slice::UnsafeSlice,
+};
+
+/// the deepest level that partition ids can represent
+pub const MAXIMUM_LEVEL: u8 = PartitionID::MAXIMUM_LEVEL;
 
 /// represents the hiearchical partition id scheme. The root id has ID 1 and
 /// children are shifted to the left by one and plus 0/1. The parent child
-/// relationship can thus be queried in constant time.
+/// relationship can thus be queried in constant time. The depth of the
+/// hierarchy is bounded by the number of bits of the underlying integer.
 #[derive(Serialize, Deserialize, Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
-pub struct PartitionID(u32);
+pub struct GenericPartitionID<T>(T);
+
+/// partition ids of up to 31 levels
+pub type PartitionID = GenericPartitionID<u32>;
+/// partition ids of up to 63 levels
+pub type PartitionID64 = GenericPartitionID<u64>;
+
+impl<T: PrimInt> GenericPartitionID<T> {
+    /// the deepest level that ids of this type can represent
+    pub const MAXIMUM_LEVEL: u8 = (8 * std::mem::size_of::<T>() - 1) as u8;
 
-impl PartitionID {
     /// Returns the root id
-    pub fn root() -> PartitionID {
-        PartitionID(1)
+    pub fn root() -> Self {
+        Self(T::one())
     }
 
     /// Returns the parent of a given ID.
     /// Note that the parent of the root id is always 1
-    pub fn parent(&self) -> PartitionID {
-        let new_id = max(1, self.0 >> 1);
-        PartitionID::new(new_id)
+    pub fn parent(&self) -> Self {
+        let new_id = max(T::one(), self.0 >> 1);
+        Self::new(new_id)
     }
 
     /// Returns a left-right ordered tuple of children for a given ID
-    pub fn children(&self) -> (PartitionID, PartitionID) {
+    pub fn children(&self) -> (Self, Self) {
         let temp = self.0 << 1;
-        (PartitionID(temp), PartitionID(temp + 1))
+        (Self(temp), Self(temp + T::one()))
     }
 
     /// Returns the left child of a ID
-    pub fn left_child(&self) -> PartitionID {
+    pub fn left_child(&self) -> Self {
         let temp = self.0 << 1;
-        PartitionID(temp)
+        Self(temp)
     }
 
     /// Returns the right child of a ID
-    pub fn right_child(&self) -> PartitionID {
+    pub fn right_child(&self) -> Self {
         let temp = self.0 << 1;
-        PartitionID(temp + 1)
+        Self(temp + T::one())
     }
 
     /// Transform ID to its left-most descendant k levels down
     pub fn inplace_leftmost_descendant(&mut self, k: usize) {
-        self.0 <<= k;
+        self.0 = self.0 << k;
     }
 
     /// Transform ID to its right-most descendant k levels down
     pub fn inplace_rightmost_descendant(&mut self, k: usize) {
         self.inplace_leftmost_descendant(k);
-        self.0 += (1 << k) - 1;
+        self.0 = self.0 + ((T::one() << k) - T::one());
     }
 
     /// Transform the ID into its left child
@@ -60,46 +99,656 @@ impl PartitionID {
         self.inplace_rightmost_descendant(1);
     }
 
-    /// Returns a new PartitionID from an u32
-    pub fn new(id: u32) -> Self {
+    /// Returns a new PartitionID from an integer
+    pub fn new(id: T) -> Self {
         // the id scheme is designed in a way that the number of leading zeros is always odd
-        assert!(id != 0);
-        PartitionID(id)
+        assert!(id != T::zero());
+        Self(id)
     }
 
     /// The level in this scheme is defined by the the number of leading zeroes.
     pub fn level(&self) -> u8 {
-        // magic number 31 := 32 - 1, as 1 is the root's ID
-        (31 - self.0.leading_zeros()).try_into().unwrap()
+        // the root's ID 1 has all but one leading zeros
+        (Self::MAXIMUM_LEVEL as u32 - self.0.leading_zeros())
+            .try_into()
+            .unwrap()
     }
 
     /// Returns whether the ID id a left child
     pub fn is_left_child(&self) -> bool {
-        self.0 % 2 == 0
+        self.0 & T::one() == T::zero()
     }
 
     /// Returns whether the ID id a right child
     pub fn is_right_child(&self) -> bool {
-        self.0 % 2 == 1
+        self.0 & T::one() == T::one()
+    }
+
+    /// Returns the ancestor of the ID on the given level. IDs that are on
+    /// that level or above are returned unchanged.
+    pub fn ancestor_at_level(&self, level: u8) -> Self {
+        let shift = self.level().saturating_sub(level);
+        Self(self.0 >> shift as usize)
+    }
+
+    /// Returns the deepest ID that both IDs descend from
+    pub fn lowest_common_ancestor(&self, other: &Self) -> Self {
+        let level = min(self.level(), other.level());
+        let (a, b) = (
+            self.ancestor_at_level(level).0,
+            other.ancestor_at_level(level).0,
+        );
+        // the ids agree on all bits above the highest differing one
+        let differing_bits = 8 * std::mem::size_of::<T>() as u32 - (a ^ b).leading_zeros();
+        Self(a >> differing_bits as usize)
+    }
+
+    /// Returns whether the ID is an ancestor of the other one, or the same ID
+    pub fn is_ancestor_of(&self, other: &Self) -> bool {
+        other.level() >= self.level() && other.ancestor_at_level(self.level()) == *self
+    }
+
+    /// Returns the leftmost and rightmost descendants on the given level. All
+    /// IDs on that level in between belong to the subtree of this ID.
+    pub fn descendant_range(&self, level: u8) -> RangeInclusive<Self> {
+        assert!(level >= self.level());
+        let k = (level - self.level()) as usize;
+        let (mut first, mut last) = (*self, *self);
+        first.inplace_leftmost_descendant(k);
+        last.inplace_rightmost_descendant(k);
+        first..=last
+    }
+
+    /// Returns an iterator over all descendants on the given level from left
+    /// to right.
+    pub fn descendants_at_level(&self, level: u8) -> impl Iterator<Item = Self> {
+        let range = self.descendant_range(level);
+        num::range_inclusive(range.start().0, range.end().0).map(Self)
     }
 }
 
-impl Display for PartitionID {
+impl<T: Display> Display for GenericPartitionID<T> {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         write!(f, "{}", self.0)
     }
 }
 
-impl From<PartitionID> for usize {
-    fn from(s: PartitionID) -> usize {
-        s.0.try_into().unwrap()
+impl<T: PrimInt> From<GenericPartitionID<T>> for usize {
+    fn from(s: GenericPartitionID<T>) -> usize {
+        s.0.to_usize().unwrap()
+    }
+}
+
+/// The algorithm that bisects each cell
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Bisector {
+    InertialFlow,
+    FlowCutter,
+    Multilevel,
+    Spectral,
+}
+
+/// Called with the level that is being computed, the number of cells that
+/// were bisected so far on that level and the number of cells to bisect.
+pub type ProgressCallback = dyn Fn(u8, usize, usize) + Send + Sync;
+
+/// Parameters of the recursive bisection. Without a maximum cell size or a
+/// number of cells, all cells are bisected to the given recursion depth.
+pub struct RecursiveBisectionConfig {
+    pub bisector: Bisector,
+    pub recursion_depth: u8,
+    /// the minimum share of nodes on either side of a cut
+    pub balance_factor: f64,
+    /// cells of at most this size are not bisected any further
+    pub minimum_cell_size: usize,
+    /// bisect until no cell is larger than this size
+    pub maximum_cell_size: Option<usize>,
+    /// the number of cells to partition into by uneven bisections
+    pub cells: Option<usize>,
+    /// number of evenly spaced directions along which cells are cut
+    pub directions: usize,
+    /// additionally cut along the principal axis of each cell's coordinates
+    pub principal_axis: bool,
+    /// improve each cut with Fiduccia-Mattheyses refinement
+    pub refine: bool,
+    /// separate cells by node separators instead of edge cuts, see
+    /// `recursive_separation`
+    pub separators: bool,
+    pub progress: Option<Box<ProgressCallback>>,
+}
+
+impl Default for RecursiveBisectionConfig {
+    fn default() -> Self {
+        Self {
+            bisector: Bisector::InertialFlow,
+            recursion_depth: 1,
+            balance_factor: 0.25,
+            minimum_cell_size: 50,
+            maximum_cell_size: None,
+            cells: None,
+            directions: 4,
+            principal_axis: false,
+            refine: false,
+            separators: false,
+            progress: None,
+        }
+    }
+}
+
+/// a cell to bisect: its edges, its node ids and the number of cells it is
+/// to be split into, if given
+type Job<E> = (Vec<E>, Vec<NodeID>, usize);
+
+/// The node separator of a bisected cell. The nodes of the separator are
+/// assigned to the left child of the cell, but are not bisected any further.
+/// Thus, the left side of the separator are the nodes below the left child
+/// that are neither in this separator nor in one of its ancestors, and the
+/// right side are the nodes below the right child.
+#[derive(Clone, Debug, PartialEq)]
+pub struct CellSeparator<T> {
+    pub cell: GenericPartitionID<T>,
+    pub separator_ids: Vec<NodeID>,
+}
+
+/// Partitions a graph by recursive bisection and returns the partition id of
+/// each node. The cells of a level are bisected in parallel. All ids end on
+/// the same level, i.e. cells that are not bisected any further are padded
+/// with left children. Cells that consist of several connected components
+/// are split along their components without cutting any edges. The width of
+/// the ids bounds the depth of the recursion.
+///
+/// # Arguments
+///
+/// * `edges` - a list of edges that represents the input graph. Inertial
+///   flow and the multilevel bisector cut across edges of low capacity,
+///   while flow cutter treats all edges alike.
+/// * `coordinates` - the coordinates of the nodes, may be empty for the
+///   multilevel bisector
+/// * `config` - the parameters of the recursive bisection
+pub fn recursive_bisection<T, E>(
+    edges: &[E],
+    coordinates: &[FPCoordinate],
+    config: &RecursiveBisectionConfig,
+) -> Vec<GenericPartitionID<T>>
+where
+    T: PrimInt + Send + Sync,
+    E: Edge<ID = NodeID> + EdgeCapacity + Copy + Send + Sync,
+{
+    recursive_separation(edges, coordinates, config).0
+}
+
+/// Partitions a graph by recursive bisection like `recursive_bisection` and
+/// additionally returns the node separator of each bisected cell, if the
+/// configuration asks for separators. Inertial flow computes minimum
+/// separators by splitting each node into an in-node and an out-node that
+/// are connected by an edge of unit capacity. The other bisectors and cells
+/// in which inertial flow does not find a separator derive their separators
+/// by covering the edges of a cut with nodes.
+///
+/// # Arguments
+///
+/// * `edges` - a list of edges that represents the input graph
+/// * `coordinates` - the coordinates of the nodes, may be empty for the
+///   multilevel bisector
+/// * `config` - the parameters of the recursive bisection
+pub fn recursive_separation<T, E>(
+    edges: &[E],
+    coordinates: &[FPCoordinate],
+    config: &RecursiveBisectionConfig,
+) -> (Vec<GenericPartitionID<T>>, Vec<CellSeparator<T>>)
+where
+    T: PrimInt + Send + Sync,
+    E: Edge<ID = NodeID> + EdgeCapacity + Copy + Send + Sync,
+{
+    assert!(
+        !coordinates.is_empty()
+            || matches!(config.bisector, Bisector::Multilevel | Bisector::Spectral),
+        "the {:?} bisector needs coordinates",
+        config.bisector
+    );
+
+    // without coordinates the number of nodes is derived from the edges
+    let number_of_nodes = edges.iter().fold(coordinates.len(), |acc, edge| {
+        max(acc, max(edge.source(), edge.target()) + 1)
+    });
+
+    // enqueue initial job for partitioning of the root node into job queue.
+    let id_vector = (0..number_of_nodes).collect_vec();
+    let job = (edges.to_vec(), id_vector, config.cells.unwrap_or(0));
+    let bisect_further = |size: usize, cells: usize| -> bool {
+        let too_large = match (config.cells, config.maximum_cell_size) {
+            (Some(_), _) => cells > 1,
+            (None, Some(maximum_cell_size)) => size > maximum_cell_size,
+            (None, None) => size > config.minimum_cell_size,
+        };
+        too_large && size > 1
+    };
+    // without a fixed depth, recursion ends when the partition ids run out of bits
+    let fixed_depth = config.cells.is_none() && config.maximum_cell_size.is_none();
+    let maximum_depth = if fixed_depth {
+        min(
+            config.recursion_depth,
+            GenericPartitionID::<T>::MAXIMUM_LEVEL,
+        )
+    } else {
+        GenericPartitionID::<T>::MAXIMUM_LEVEL
+    };
+    let mut current_job_queue = Vec::new();
+    if fixed_depth || bisect_further(job.1.len(), job.2) {
+        current_job_queue.push(job);
+    }
+
+    let mut current_level = 0;
+    let mut partition_ids_vec = vec![GenericPartitionID::root(); number_of_nodes];
+    let partition_ids = UnsafeSlice::new(&mut partition_ids_vec);
+    let separators = Mutex::new(Vec::new());
+    // assigns the sides of a cut to the nodes of a job and derives the jobs of both halves
+    let assign_and_split = |job: &Job<E>, result: FlowResult, separator_ids: Vec<NodeID>| {
+        debug!("partitioning and assigning ids for all nodes");
+        if let (true, Some(first)) = (config.separators, job.1.first()) {
+            let cell = unsafe { *partition_ids.get(*first) };
+            separators.lock().unwrap().push(CellSeparator {
+                cell,
+                separator_ids: separator_ids.clone(),
+            });
+        }
+
+        (result.left_ids.iter().chain(&separator_ids)).for_each(|id| unsafe {
+            partition_ids.get(*id).inplace_left_child();
+        });
+        (result.right_ids).iter().for_each(|id| unsafe {
+            partition_ids.get(*id).inplace_right_child();
+        });
+
+        // partition edge and node id sets for the next iteration
+        debug!("generating next level edges");
+        // separator nodes are not bisected any further
+        let separator: HashSet<NodeID> = separator_ids.into_iter().collect();
+        // TODO: don't copy, but partition in place
+        let (left_edges, right_edges): (Vec<_>, Vec<_>) = job
+            .0
+            .iter()
+            .filter(|edge| unsafe {
+                partition_ids.get(edge.source()) == partition_ids.get(edge.target())
+            })
+            .filter(|edge| {
+                !separator.contains(&edge.source()) && !separator.contains(&edge.target())
+            })
+            .partition(|edge| unsafe { partition_ids.get(edge.source()).is_left_child() });
+        debug!("generating next level ids");
+
+        // iterate on both halves
+        let (left_cells, right_cells) =
+            split_cells(job.2, result.left_ids.len(), result.right_ids.len());
+        let halves = [
+            (left_edges, result.left_ids, left_cells),
+            (right_edges, result.right_ids, right_cells),
+        ];
+        let mut next_jobs = Vec::new();
+        for (edges, ids, cells) in halves {
+            if bisect_further(ids.len(), cells) {
+                next_jobs.push((edges, ids, cells));
+            }
+        }
+        next_jobs
+    };
+    while !current_job_queue.is_empty() && current_level < maximum_depth {
+        let number_of_jobs = current_job_queue.len();
+        let finished_jobs = AtomicUsize::new(0);
+        if let Some(progress) = &config.progress {
+            progress(current_level + 1, 0, number_of_jobs);
+        }
+
+        let next_job_queue = current_job_queue
+            .par_iter()
+            .flat_map(|job| {
+                let next_jobs = bisect_job(job, coordinates, config, &assign_and_split);
+                if let Some(progress) = &config.progress {
+                    let finished = finished_jobs.fetch_add(1, Ordering::Relaxed) + 1;
+                    progress(current_level + 1, finished, number_of_jobs);
+                }
+                next_jobs
+            })
+            .collect();
+        current_level += 1;
+        current_job_queue = next_job_queue;
+    }
+
+    if !fixed_depth && !current_job_queue.is_empty() {
+        warn!(
+            "{} cells remain larger than requested at depth {maximum_depth}",
+            current_job_queue.len()
+        );
+    }
+
+    // cells that were not bisected further are padded to the deepest level
+    let final_level = if fixed_depth {
+        maximum_depth
+    } else {
+        partition_ids_vec
+            .iter()
+            .map(|id| id.level())
+            .max()
+            .unwrap_or(0)
+    };
+    for id in &mut partition_ids_vec {
+        let level_difference = (final_level - id.level()) as usize;
+        id.inplace_leftmost_descendant(level_difference);
+    }
+    let mut separators = separators.into_inner().unwrap();
+    separators.sort_unstable_by_key(|separator| separator.cell);
+    (partition_ids_vec, separators)
+}
+
+/// Bisects the cell of a single job and returns the jobs of its halves
+fn bisect_job<E, F>(
+    job: &Job<E>,
+    coordinates: &[FPCoordinate],
+    config: &RecursiveBisectionConfig,
+    assign_and_split: &F,
+) -> Vec<Job<E>>
+where
+    E: Edge<ID = NodeID> + EdgeCapacity + Copy + Send + Sync,
+    F: Fn(&Job<E>, FlowResult, Vec<NodeID>) -> Vec<Job<E>>,
+{
+    // the left side gets the smaller half of the cells
+    let ratio = if job.2 > 1 {
+        (job.2 / 2) as f64 / job.2 as f64
+    } else {
+        0.5
+    };
+
+    // disconnected cells are split along their components without cutting edges
+    let capacity = match (config.cells, config.maximum_cell_size) {
+        (Some(_), _) => (job.1.len() + job.2 - 1) / max(1, job.2),
+        (None, Some(maximum_cell_size)) => maximum_cell_size,
+        (None, None) => config.minimum_cell_size,
+    };
+    if let Some(result) = split_components(&job.0, &job.1, capacity, ratio) {
+        debug!(
+            "split components into {} and {} nodes",
+            result.left_ids.len(),
+            result.right_ids.len()
+        );
+        return assign_and_split(job, result, Vec::new());
+    }
+
+    // the bisectors need more than two nodes, so each node gets a side of its own
+    if let [first, second] = job.1[..] {
+        let flow = job
+            .0
+            .iter()
+            .filter(|edge| edge.source() == first && edge.target() == second)
+            .map(|edge| edge.capacity())
+            .sum();
+        let result = FlowResult {
+            flow,
+            balance: 0.5,
+            left_ids: vec![first],
+            right_ids: vec![second],
+        };
+        return assign_cut(job, result, config, assign_and_split);
+    }
+
+    let mut axes = Axes::with_directions(config.directions);
+    if config.principal_axis {
+        axes.push(Axis::principal(&job.1, coordinates));
+    }
+
+    if config.separators && config.bisector == Bisector::InertialFlow {
+        // a separator has at most as many nodes as there are nodes in the cell
+        let upper_bound = Arc::new(AtomicI32::new(min(job.1.len(), i32::MAX as usize) as i32));
+        let best_separator = axes
+            .as_slice()
+            .par_iter()
+            .map(|axis| {
+                inertial_flow::separator_sub_step_with_ratio(
+                    &job.0,
+                    &job.1,
+                    coordinates,
+                    axis,
+                    config.balance_factor,
+                    ratio,
+                    upper_bound.clone(),
+                )
+            })
+            .min_by(separator_cmp)
+            .filter(|separator| !separator.left_ids.is_empty() && !separator.right_ids.is_empty());
+        if let Some(separator) = best_separator {
+            debug!(
+                "best separator: {}, balance: {:.3}",
+                separator.flow, separator.balance
+            );
+            let (result, separator_ids) = split_off_separator(separator);
+            return assign_and_split(job, result, separator_ids);
+        }
+        // sources and targets are adjacent, thus the edges of a cut are covered
+        debug!("no separator found, covering a cut instead");
+    }
+
+    let best_max_flow = match config.bisector {
+        Bisector::InertialFlow => {
+            // we use the total capacity as an upper bound to the cut size
+            let total_capacity = job.0.iter().map(|edge| edge.capacity() as i64).sum::<i64>();
+            let upper_bound = Arc::new(AtomicI32::new(min(total_capacity, i32::MAX as i64) as i32));
+            // run inertial flow on all axes
+            axes.as_slice()
+                .par_iter()
+                .map(|axis| -> FlowResult {
+                    inertial_flow::sub_step_with_ratio(
+                        &job.0,
+                        &job.1,
+                        coordinates,
+                        axis,
+                        config.balance_factor,
+                        ratio,
+                        upper_bound.clone(),
+                    )
+                })
+                .min_by(flow_cmp)
+        }
+        Bisector::FlowCutter => {
+            // flow cutter augments along unit capacities
+            let edges = job
+                .0
+                .iter()
+                .map(|edge| TrivialEdge {
+                    source: edge.source(),
+                    target: edge.target(),
+                })
+                .collect_vec();
+            // run flow cutter from the extreme nodes of all axes
+            let fronts = axes
+                .as_slice()
+                .par_iter()
+                .flat_map(|axis| flow_cutter::sub_step(&edges, &job.1, coordinates, axis))
+                .collect::<Vec<_>>();
+            flow_cutter::best_cut_with_ratio(fronts, config.balance_factor, ratio)
+        }
+        Bisector::Multilevel => Some(multilevel::bisect_with_ratio(
+            &job.0,
+            &job.1,
+            config.balance_factor,
+            ratio,
+        )),
+        Bisector::Spectral => {
+            let total_capacity = job.0.iter().map(|edge| edge.capacity() as i64).sum::<i64>();
+            let upper_bound = Arc::new(AtomicI32::new(min(total_capacity, i32::MAX as i64) as i32));
+            Some(spectral::sub_step_with_ratio(
+                &job.0,
+                &job.1,
+                config.balance_factor,
+                ratio,
+                upper_bound,
+            ))
+        }
+    };
+
+    let mut result = best_max_flow.unwrap();
+    if config.refine {
+        // post-process the cut by local improvements
+        result = fm_refinement::refine_with_ratio(&job.0, &result, config.balance_factor, ratio);
+    }
+    debug!(
+        "best max-flow: {}, balance: {:.3}",
+        result.flow, result.balance
+    );
+    assign_cut(job, result, config, assign_and_split)
+}
+
+/// Assigns the sides of a cut, whose edges are covered by a node separator
+/// first if the configuration asks for separators
+fn assign_cut<E, F>(
+    job: &Job<E>,
+    result: FlowResult,
+    config: &RecursiveBisectionConfig,
+    assign_and_split: &F,
+) -> Vec<Job<E>>
+where
+    E: Edge<ID = NodeID> + EdgeCapacity + Copy,
+    F: Fn(&Job<E>, FlowResult, Vec<NodeID>) -> Vec<Job<E>>,
+{
+    if config.separators {
+        let separator = SeparatorResult::from_cut(&job.0, &result);
+        let (result, separator_ids) = split_off_separator(separator);
+        return assign_and_split(job, result, separator_ids);
+    }
+    assign_and_split(job, result, Vec::new())
+}
+
+/// Returns both sides of a separator as a cut, whose size is the number of
+/// separator nodes, together with the separator nodes
+fn split_off_separator(separator: SeparatorResult) -> (FlowResult, Vec<NodeID>) {
+    let result = FlowResult {
+        flow: separator.flow,
+        balance: separator.balance,
+        left_ids: separator.left_ids,
+        right_ids: separator.right_ids,
+    };
+    (result, separator.separator_ids)
+}
+
+/// Splits the cell of a job along its connected components, if it has more
+/// than one. Components are bin-packed into groups of at most the given
+/// capacity, unless that leaves a single group, and the groups are spread
+/// over both sides such that the left side gets the given share of nodes.
+fn split_components(
+    edges: &[impl Edge<ID = NodeID>],
+    ids: &[NodeID],
+    capacity: usize,
+    ratio: f64,
+) -> Option<FlowResult> {
+    let universe_size = ids.iter().fold(0, |acc, id| max(acc, id + 1));
+    let mut renumbering_table = RenumberingTable::new_with_size_hint(universe_size, ids.len());
+    for (local_id, id) in ids.iter().enumerate() {
+        renumbering_table.set(*id, local_id);
+    }
+    let mut union_find = UnionFind::new(ids.len());
+    for edge in edges {
+        union_find.union(
+            renumbering_table.get(edge.source()),
+            renumbering_table.get(edge.target()),
+        );
+    }
+
+    // number the components by their representatives
+    let mut component_of_root = vec![usize::MAX; ids.len()];
+    let mut component_sizes = Vec::new();
+    let components = (0..ids.len())
+        .map(|local_id| {
+            let root = union_find.find(local_id);
+            if component_of_root[root] == usize::MAX {
+                component_of_root[root] = component_sizes.len();
+                component_sizes.push(0);
+            }
+            component_sizes[component_of_root[root]] += 1;
+            component_of_root[root]
+        })
+        .collect_vec();
+    if component_sizes.len() < 2 {
+        return None;
+    }
+
+    // pack the components by descending size
+    let by_size = (0..component_sizes.len())
+        .sorted_by_key(|component| std::cmp::Reverse(component_sizes[*component]))
+        .collect_vec();
+    let items = by_size
+        .iter()
+        .map(|component| component_sizes[*component] as u32)
+        .collect_vec();
+    let mut bins = bin_pack_assignment(&items, capacity as u32);
+    if bins.last() == Some(&0) {
+        bins = (0..items.len() as u32).collect();
+    }
+    let mut group_of_component = vec![0; component_sizes.len()];
+    let mut group_sizes = vec![0; *bins.last().unwrap() as usize + 1];
+    for (component, bin) in by_size.iter().zip(&bins) {
+        group_of_component[*component] = *bin as usize;
+        group_sizes[*bin as usize] += component_sizes[*component];
+    }
+
+    // assign the largest groups first to the side that is furthest below its share
+    let mut is_left_group = vec![false; group_sizes.len()];
+    let (mut left_size, mut right_size) = (0, 0);
+    for group in
+        (0..group_sizes.len()).sorted_by_key(|group| std::cmp::Reverse(group_sizes[*group]))
+    {
+        if left_size as f64 / ratio <= right_size as f64 / (1. - ratio) {
+            is_left_group[group] = true;
+            left_size += group_sizes[group];
+        } else {
+            right_size += group_sizes[group];
+        }
+    }
+
+    let (left_ids, right_ids): (Vec<_>, Vec<_>) = ids
+        .iter()
+        .zip(&components)
+        .partition(|(_, component)| is_left_group[group_of_component[**component]]);
+    Some(FlowResult {
+        flow: 0,
+        balance: min(left_size, right_size) as f64 / ids.len() as f64,
+        left_ids: left_ids.into_iter().map(|(id, _)| *id).collect(),
+        right_ids: right_ids.into_iter().map(|(id, _)| *id).collect(),
+    })
+}
+
+/// Splits the number of cells of a job proportionally to the sizes of both
+/// sides of its cut, such that each side gets at least one cell.
+fn split_cells(cells: usize, left_size: usize, right_size: usize) -> (usize, usize) {
+    if cells < 2 {
+        return (0, 0);
     }
+    let proportional =
+        (cells as f64 * left_size as f64 / (left_size + right_size) as f64).round() as usize;
+    // neither side can be split into more cells than it has nodes
+    let lower = max(1, cells.saturating_sub(right_size));
+    let upper = max(lower, min(cells - 1, left_size));
+    let left_cells = proportional.clamp(lower, upper);
+    (left_cells, cells - left_cells)
 }
 
 #[cfg(test)]
 mod tests {
+    use std::{
+        collections::HashSet,
+        sync::{
+            atomic::{AtomicUsize, Ordering},
+            Arc,
+        },
+    };
+
+    use itertools::Itertools;
 
-    use crate::partition::PartitionID;
+    use crate::{
+        edge::TrivialEdge,
+        partition::{
+            recursive_bisection, recursive_separation, Bisector, GenericPartitionID, PartitionID,
+            PartitionID64, RecursiveBisectionConfig,
+        },
+        test_utils::{grid, grid_coordinates},
+    };
 
     #[test]
     fn parent_id() {
@@ -141,7 +790,7 @@ mod tests {
 
     #[test]
     fn left_right_childs() {
-        let id = PartitionID(12345);
+        let id = GenericPartitionID(12345);
         let (left_child, right_child) = id.children();
         assert_eq!(left_child, id.left_child());
         assert_eq!(right_child, id.right_child());
@@ -149,7 +798,7 @@ mod tests {
 
     #[test]
     fn is_left_right_child() {
-        let id = PartitionID(12345);
+        let id = GenericPartitionID(12345);
         let (left_child, right_child) = id.children();
         assert_eq!(left_child, id.left_child());
         assert_eq!(right_child, id.right_child());
@@ -159,7 +808,7 @@ mod tests {
 
     #[test]
     fn inplace_left_child() {
-        let mut id = PartitionID(12345);
+        let mut id = GenericPartitionID(12345);
         let (left_child, _) = id.children();
         id.inplace_left_child();
         assert_eq!(left_child, id);
@@ -167,7 +816,7 @@ mod tests {
 
     #[test]
     fn inplace_right_child() {
-        let mut id = PartitionID(12345);
+        let mut id = GenericPartitionID(12345);
         let (_, right_child) = id.children();
         id.inplace_right_child();
         assert_eq!(right_child, id);
@@ -175,14 +824,14 @@ mod tests {
 
     #[test]
     fn into_usize() {
-        let id = PartitionID(12345);
+        let id = GenericPartitionID(12345);
         let id_usize = usize::from(id);
         assert_eq!(12345, id_usize);
     }
 
     #[test]
     fn inplace_leftmost_descendant() {
-        let id = PartitionID(1);
+        let id = GenericPartitionID(1);
         let mut current = id;
         for i in 1..30 {
             let mut id = id.clone();
@@ -194,7 +843,7 @@ mod tests {
 
     #[test]
     fn inplace_rightmost_descendant() {
-        let id = PartitionID(1);
+        let id = GenericPartitionID(1);
         let mut current = id;
         for i in 1..30 {
             let mut id = id.clone();
@@ -207,9 +856,9 @@ mod tests {
     #[test]
     fn display() {
         for i in 0..100 {
-            let id = PartitionID(i);
+            let id = GenericPartitionID(i);
             let string = format!("{}", id);
-            let recast_id = PartitionID(string.parse::<u32>().unwrap());
+            let recast_id = GenericPartitionID(string.parse::<u32>().unwrap());
             assert_eq!(id, recast_id);
         }
     }
@@ -217,10 +866,267 @@ mod tests {
     #[test]
     fn partial_eq() {
         for i in 0..100 {
-            let id = PartitionID(i);
+            let id = GenericPartitionID(i);
             let string = format!("{}", id);
-            let recast_id = PartitionID(string.parse::<u32>().unwrap());
+            let recast_id = GenericPartitionID(string.parse::<u32>().unwrap());
             assert_eq!(id, recast_id);
         }
     }
+
+    #[test]
+    fn ancestor_at_level() {
+        let id = PartitionID::new(0b1011_0110);
+        assert_eq!(id.ancestor_at_level(7), id);
+        assert_eq!(id.ancestor_at_level(9), id);
+        assert_eq!(id.ancestor_at_level(4), PartitionID::new(0b1_0110));
+        assert_eq!(id.ancestor_at_level(1), PartitionID::new(0b10));
+        assert_eq!(id.ancestor_at_level(0), PartitionID::root());
+    }
+
+    #[test]
+    fn recursive_bisection_to_fixed_depth() {
+        let (edges, coordinates) = (grid(16, 16), grid_coordinates(16, 16));
+        let progress_calls = Arc::new(AtomicUsize::new(0));
+        let config = RecursiveBisectionConfig {
+            recursion_depth: 2,
+            minimum_cell_size: 16,
+            progress: Some(Box::new({
+                let progress_calls = progress_calls.clone();
+                move |level, finished, total| {
+                    assert!((1..=2).contains(&level));
+                    assert!(finished <= total);
+                    progress_calls.fetch_add(1, Ordering::Relaxed);
+                }
+            })),
+            ..Default::default()
+        };
+        let partition_ids: Vec<PartitionID> = recursive_bisection(&edges, &coordinates, &config);
+        assert_eq!(partition_ids.len(), 256);
+        assert!(partition_ids.iter().all(|id| id.level() == 2));
+        let cells = partition_ids.iter().counts();
+        assert_eq!(cells.len(), 4);
+        assert!(cells.values().all(|size| *size >= 16));
+        // one call at the start of each level and one per bisected cell
+        assert_eq!(progress_calls.load(Ordering::Relaxed), 2 + 1 + 2);
+    }
+
+    #[test]
+    fn recursive_bisection_into_cells() {
+        let (edges, coordinates) = (grid(12, 12), grid_coordinates(12, 12));
+        for bisector in [Bisector::InertialFlow, Bisector::Multilevel] {
+            let config = RecursiveBisectionConfig {
+                bisector,
+                cells: Some(3),
+                ..Default::default()
+            };
+            let partition_ids: Vec<PartitionID> =
+                recursive_bisection(&edges, &coordinates, &config);
+            assert_eq!(partition_ids.iter().unique().count(), 3);
+        }
+    }
+
+    #[test]
+    fn spectral_recursive_bisection() {
+        // the spectral bisector does not need coordinates
+        let edges = grid(16, 16);
+        let config = RecursiveBisectionConfig {
+            bisector: Bisector::Spectral,
+            recursion_depth: 2,
+            ..Default::default()
+        };
+        let partition_ids: Vec<PartitionID> = recursive_bisection(&edges, &[], &config);
+        assert_eq!(partition_ids.len(), 256);
+        let cells = partition_ids.iter().counts();
+        assert_eq!(cells.len(), 4);
+        assert!(cells.values().all(|size| *size >= 16));
+    }
+
+    #[test]
+    fn recursive_separation_of_grid() {
+        let (edges, coordinates) = (grid(16, 16), grid_coordinates(16, 16));
+        for bisector in [Bisector::InertialFlow, Bisector::Multilevel] {
+            let config = RecursiveBisectionConfig {
+                bisector,
+                recursion_depth: 2,
+                minimum_cell_size: 16,
+                separators: true,
+                ..Default::default()
+            };
+            let (partition_ids, separators): (Vec<PartitionID>, _) =
+                recursive_separation(&edges, &coordinates, &config);
+            assert_eq!(partition_ids.len(), 256);
+            assert_eq!(separators.len(), 3);
+            assert_eq!(separators[0].cell, PartitionID::root());
+
+            // separators are small and no edge connects both sides of a cell
+            for separator in &separators {
+                assert!(!separator.separator_ids.is_empty());
+                assert!(separator.separator_ids.len() <= 16);
+                let level = separator.cell.level() + 1;
+                let (left, right) = separator.cell.children();
+                // separator nodes of this cell and its ancestors are on no side
+                let separated = separators
+                    .iter()
+                    .filter(|other| other.cell.is_ancestor_of(&separator.cell))
+                    .flat_map(|other| &other.separator_ids)
+                    .collect::<HashSet<_>>();
+                let side = |node: usize| {
+                    if separated.contains(&node) {
+                        None
+                    } else {
+                        Some(partition_ids[node].ancestor_at_level(level))
+                    }
+                };
+                for edge in &edges {
+                    let sides = (side(edge.source), side(edge.target));
+                    assert!(sides != (Some(left), Some(right)));
+                }
+                // separator nodes belong to the left child
+                for node in &separator.separator_ids {
+                    assert_eq!(partition_ids[*node].ancestor_at_level(level), left);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn recursive_bisection_into_single_nodes() {
+        // cells of two nodes are split without running a bisector
+        let (edges, coordinates) = (grid(4, 4), grid_coordinates(4, 4));
+        for (bisector, maximum_cell_size, cells) in [
+            (Bisector::InertialFlow, Some(1), None),
+            (Bisector::Multilevel, Some(1), None),
+            (Bisector::InertialFlow, None, Some(16)),
+        ] {
+            let config = RecursiveBisectionConfig {
+                bisector,
+                maximum_cell_size,
+                cells,
+                ..Default::default()
+            };
+            let partition_ids: Vec<PartitionID> =
+                recursive_bisection(&edges, &coordinates, &config);
+            assert_eq!(partition_ids.iter().unique().count(), 16);
+        }
+    }
+
+    #[test]
+    fn recursive_bisection_of_components() {
+        // two disconnected grids are split without cutting any edges
+        let mut edges = grid(8, 8);
+        let offset = edges.clone().into_iter().map(|edge| TrivialEdge {
+            source: edge.source + 64,
+            target: edge.target + 64,
+        });
+        edges.extend(offset);
+        let config = RecursiveBisectionConfig {
+            bisector: Bisector::Multilevel,
+            maximum_cell_size: Some(64),
+            ..Default::default()
+        };
+        let partition_ids: Vec<PartitionID> = recursive_bisection(&edges, &[], &config);
+        assert!(partition_ids[..64].iter().all(|id| *id == partition_ids[0]));
+        assert!(partition_ids[64..]
+            .iter()
+            .all(|id| *id == partition_ids[64]));
+        assert_ne!(partition_ids[0], partition_ids[64]);
+    }
+
+    #[test]
+    fn lowest_common_ancestor() {
+        let a = PartitionID::new(0b1_0110);
+        assert_eq!(a.lowest_common_ancestor(&a), a);
+        assert_eq!(
+            a.lowest_common_ancestor(&PartitionID::new(0b1_0111)),
+            PartitionID::new(0b1_011)
+        );
+        assert_eq!(
+            a.lowest_common_ancestor(&PartitionID::new(0b1_0010)),
+            PartitionID::new(0b1_0)
+        );
+        // ids on different levels
+        assert_eq!(
+            a.lowest_common_ancestor(&PartitionID::new(0b1_01)),
+            PartitionID::new(0b1_01)
+        );
+        assert_eq!(
+            a.lowest_common_ancestor(&PartitionID::new(0b1_1)),
+            PartitionID::root()
+        );
+    }
+
+    #[test]
+    fn descendants() {
+        let id = PartitionID::new(0b1_01);
+        assert_eq!(
+            id.descendants_at_level(4).collect::<Vec<_>>(),
+            [0b1_0100, 0b1_0101, 0b1_0110, 0b1_0111].map(PartitionID::new)
+        );
+        assert_eq!(id.descendants_at_level(2).collect::<Vec<_>>(), vec![id]);
+        assert_eq!(
+            id.descendant_range(4),
+            PartitionID::new(0b1_0100)..=PartitionID::new(0b1_0111)
+        );
+
+        assert!(id.is_ancestor_of(&id));
+        assert!(id.is_ancestor_of(&PartitionID::new(0b1_0110)));
+        assert!(!id.is_ancestor_of(&PartitionID::new(0b1_1010)));
+        assert!(!id.is_ancestor_of(&PartitionID::root()));
+
+        // ids on one level sort by subtree
+        let mut ids = [0b1_110, 0b1_010, 0b1_111, 0b1_011].map(PartitionID::new);
+        ids.sort();
+        let subtree = id.descendant_range(3);
+        assert!(ids[..2].iter().all(|id| subtree.contains(id)));
+        assert!(!ids[2..].iter().any(|id| subtree.contains(id)));
+    }
+
+    #[test]
+    fn wide_ids() {
+        assert_eq!(PartitionID::MAXIMUM_LEVEL, 31);
+        assert_eq!(PartitionID64::MAXIMUM_LEVEL, 63);
+
+        let mut id = PartitionID64::root();
+        id.inplace_rightmost_descendant(63);
+        assert_eq!(id.level(), 63);
+        assert!(id.is_right_child());
+        assert_eq!(id.ancestor_at_level(1), PartitionID64::root().right_child());
+        assert_eq!(
+            id.lowest_common_ancestor(&PartitionID64::new(1 << 63)),
+            PartitionID64::root()
+        );
+        assert_eq!(usize::from(id.ancestor_at_level(4)), 0b1_1111);
+    }
+
+    #[test]
+    fn recursive_bisection_beyond_31_levels() {
+        // a path is bisected until all cells consist of single nodes
+        let edges = (0..63)
+            .flat_map(|u| {
+                [
+                    TrivialEdge {
+                        source: u,
+                        target: u + 1,
+                    },
+                    TrivialEdge {
+                        source: u + 1,
+                        target: u,
+                    },
+                ]
+            })
+            .collect_vec();
+        let config = RecursiveBisectionConfig {
+            bisector: Bisector::Multilevel,
+            recursion_depth: 40,
+            minimum_cell_size: 1,
+            ..Default::default()
+        };
+        let partition_ids: Vec<PartitionID64> = recursive_bisection(&edges, &[], &config);
+        assert_eq!(partition_ids.iter().unique().count(), 64);
+        assert!(partition_ids.iter().all(|id| id.level() == 40));
+
+        // narrow ids stop at their deepest level
+        let partition_ids: Vec<PartitionID> = recursive_bisection(&edges, &[], &config);
+        assert!(partition_ids.iter().all(|id| id.level() == 31));
+    }
 }
@@ -0,0 +1,195 @@
+//! Quality metrics of hierarchical partitions. For each level of the partition
+//! the cells are derived from the ancestors of the nodes' partition ids, and
+//! their sizes, boundaries and connectivity are reported. All metrics are
+//! serializable, e.g. into a JSON report.
+use std::collections::BTreeMap;
+
+use serde::Serialize;
+
+use crate::{edge::TrivialEdge, partition::PartitionID, union_find::UnionFind};
+
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct CellMetrics {
+    pub id: PartitionID,
+    pub size: usize,
+    /// number of nodes with an edge to another cell
+    pub boundary_nodes: usize,
+    /// number of connected components of the subgraph induced by the cell
+    pub components: usize,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct LevelMetrics {
+    pub level: u8,
+    pub number_of_cells: usize,
+    pub min_cell_size: usize,
+    pub max_cell_size: usize,
+    pub average_cell_size: f64,
+    /// number of (directed) input edges between different cells
+    pub cut_edges: usize,
+    pub boundary_nodes: usize,
+    /// relative excess of the largest cell over the average cell size
+    pub imbalance: f64,
+    pub disconnected_cells: usize,
+    pub cells: Vec<CellMetrics>,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct PartitionMetrics {
+    pub number_of_nodes: usize,
+    pub number_of_edges: usize,
+    pub levels: Vec<LevelMetrics>,
+}
+
+/// Computes the metrics of all levels of a partition, starting at level 1.
+///
+/// # Arguments
+///
+/// * `edges` - a list of edges that represents the input graph
+/// * `partition_ids` - the partition id of each node
+pub fn compute(edges: &[TrivialEdge], partition_ids: &[PartitionID]) -> PartitionMetrics {
+    let deepest_level = partition_ids.iter().map(|id| id.level()).max().unwrap_or(0);
+    PartitionMetrics {
+        number_of_nodes: partition_ids.len(),
+        number_of_edges: edges.len(),
+        levels: (1..=deepest_level)
+            .map(|level| level_metrics(edges, partition_ids, level))
+            .collect(),
+    }
+}
+
+/// Computes the metrics of the cells on a single level of a partition
+pub fn level_metrics(
+    edges: &[TrivialEdge],
+    partition_ids: &[PartitionID],
+    level: u8,
+) -> LevelMetrics {
+    let cell_ids = partition_ids
+        .iter()
+        .map(|id| id.ancestor_at_level(level))
+        .collect::<Vec<_>>();
+
+    // cells are ordered by their ids
+    let mut cells: BTreeMap<PartitionID, CellMetrics> = BTreeMap::new();
+    for id in &cell_ids {
+        cells
+            .entry(*id)
+            .or_insert(CellMetrics {
+                id: *id,
+                size: 0,
+                boundary_nodes: 0,
+                components: 0,
+            })
+            .size += 1;
+    }
+
+    let mut cut_edges = 0;
+    let mut is_boundary = vec![false; partition_ids.len()];
+    let mut union_find = UnionFind::new(partition_ids.len());
+    for edge in edges {
+        if cell_ids[edge.source] == cell_ids[edge.target] {
+            union_find.union(edge.source, edge.target);
+        } else {
+            cut_edges += 1;
+            is_boundary[edge.source] = true;
+            is_boundary[edge.target] = true;
+        }
+    }
+
+    for (node, id) in cell_ids.iter().enumerate() {
+        let cell = cells.get_mut(id).unwrap();
+        if is_boundary[node] {
+            cell.boundary_nodes += 1;
+        }
+        // each component has exactly one representative
+        if union_find.find(node) == node {
+            cell.components += 1;
+        }
+    }
+
+    let cells = cells.into_values().collect::<Vec<_>>();
+    let number_of_cells = cells.len();
+    let min_cell_size = cells.iter().map(|cell| cell.size).min().unwrap_or(0);
+    let max_cell_size = cells.iter().map(|cell| cell.size).max().unwrap_or(0);
+    let average_cell_size = partition_ids.len() as f64 / number_of_cells.max(1) as f64;
+    let imbalance = if number_of_cells == 0 {
+        0.
+    } else {
+        max_cell_size as f64 / average_cell_size - 1.
+    };
+
+    LevelMetrics {
+        level,
+        number_of_cells,
+        min_cell_size,
+        max_cell_size,
+        average_cell_size,
+        cut_edges,
+        boundary_nodes: is_boundary.iter().filter(|boundary| **boundary).count(),
+        imbalance,
+        disconnected_cells: cells.iter().filter(|cell| cell.components > 1).count(),
+        cells,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{edge::TrivialEdge, partition::PartitionID, partition_metrics::compute};
+
+    // a path of six nodes 0-1-2-3-4-5 without the edge 4-5
+    fn edges() -> Vec<TrivialEdge> {
+        [(0, 1), (1, 2), (2, 3), (3, 4)]
+            .iter()
+            .flat_map(|(u, v)| {
+                [
+                    TrivialEdge {
+                        source: *u,
+                        target: *v,
+                    },
+                    TrivialEdge {
+                        source: *v,
+                        target: *u,
+                    },
+                ]
+            })
+            .collect()
+    }
+
+    #[test]
+    fn two_levels() {
+        // cells on level 1 are {0, 1, 2, 3} and {4, 5}, on level 2 {0, 1}, {2, 3} and {4, 5}
+        let partition_ids = [4, 4, 5, 5, 6, 6].map(PartitionID::new).to_vec();
+        let metrics = compute(&edges(), &partition_ids);
+        assert_eq!(metrics.number_of_nodes, 6);
+        assert_eq!(metrics.levels.len(), 2);
+
+        let level1 = &metrics.levels[0];
+        assert_eq!(level1.number_of_cells, 2);
+        assert_eq!(level1.cut_edges, 2);
+        assert_eq!(level1.boundary_nodes, 2);
+        assert_eq!(level1.min_cell_size, 2);
+        assert_eq!(level1.max_cell_size, 4);
+        assert_eq!(level1.imbalance, 4. / 3. - 1.);
+        assert_eq!(level1.disconnected_cells, 1);
+        assert_eq!(level1.cells[0].id, PartitionID::new(2));
+        assert_eq!(level1.cells[1].components, 2);
+
+        let level2 = &metrics.levels[1];
+        assert_eq!(level2.number_of_cells, 3);
+        assert_eq!(level2.cut_edges, 4);
+        assert_eq!(level2.boundary_nodes, 4);
+        assert_eq!(level2.imbalance, 0.);
+        assert_eq!(level2.cells[2].boundary_nodes, 1);
+        assert_eq!(level2.cells[2].components, 2);
+    }
+
+    #[test]
+    fn json_report() {
+        let partition_ids = [2, 2, 2, 3, 3, 3].map(PartitionID::new).to_vec();
+        let metrics = compute(&edges(), &partition_ids);
+        let json = serde_json::to_value(&metrics).unwrap();
+        assert_eq!(json["levels"][0]["number_of_cells"], 2);
+        assert_eq!(json["levels"][0]["cells"][1]["id"], 3);
+        assert_eq!(json["levels"][0]["cells"][1]["components"], 2);
+    }
+}
@@ -0,0 +1,304 @@
+//! Cache-friendly node orders for static graphs. Queries touch the nodes of a
+//! neighborhood one after another, so storing nearby nodes at nearby ids
+//! reduces the number of cache misses. Orders are derived from traversals of
+//! the graph, from space-filling curves through the coordinates, or from the
+//! cells of a partition.
+use std::collections::VecDeque;
+
+use itertools::Itertools;
+
+use crate::{
+    geometry::primitives::FPCoordinate,
+    graph::{Graph, NodeID},
+    partition::PartitionID,
+    space_filling_curve::Curve,
+    static_graph::StaticGraph,
+};
+
+/// A renumbering of nodes together with its inverse
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Permutation {
+    /// the new id of each old id
+    pub forward: Vec<NodeID>,
+    /// the old id of each new id
+    pub backward: Vec<NodeID>,
+}
+
+impl Permutation {
+    /// Creates the permutation that numbers the nodes in the given order, i.e.
+    /// the first node of the order gets id 0.
+    pub fn from_order(order: Vec<NodeID>) -> Self {
+        let mut forward = vec![NodeID::MAX; order.len()];
+        for (new_id, old_id) in order.iter().enumerate() {
+            assert_eq!(forward[*old_id], NodeID::MAX, "node {old_id} is repeated");
+            forward[*old_id] = new_id;
+        }
+        Self {
+            forward,
+            backward: order,
+        }
+    }
+
+    pub fn identity(number_of_nodes: usize) -> Self {
+        Self::from_order((0..number_of_nodes).collect())
+    }
+
+    pub fn len(&self) -> usize {
+        self.forward.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.forward.is_empty()
+    }
+
+    /// Returns the inverse permutation that restores the original ids
+    pub fn inverse(&self) -> Self {
+        Self {
+            forward: self.backward.clone(),
+            backward: self.forward.clone(),
+        }
+    }
+
+    /// Moves per-node values, e.g. coordinates, to the new ids of their nodes
+    pub fn apply<V: Clone>(&self, values: &[V]) -> Vec<V> {
+        assert_eq!(values.len(), self.len());
+        self.backward
+            .iter()
+            .map(|old_id| values[*old_id].clone())
+            .collect()
+    }
+}
+
+/// The orders in which nodes can be renumbered
+#[derive(Clone, Copy, Debug)]
+pub enum NodeOrder<'a> {
+    /// the order of a breadth-first traversal
+    BreadthFirst,
+    /// the preorder of a depth-first traversal
+    DepthFirst,
+    /// the order of the coordinates along a space-filling curve
+    SpaceFillingCurve(Curve),
+    /// the cells of a partition one after another
+    Partition(&'a [PartitionID]),
+}
+
+/// Returns the nodes in the order of a breadth-first traversal. Each
+/// traversal starts from the smallest node id that was not visited yet.
+pub fn breadth_first_order<T: Ord + Copy>(graph: &StaticGraph<T>) -> Vec<NodeID> {
+    let mut visited = vec![false; graph.number_of_nodes()];
+    let mut order = Vec::with_capacity(graph.number_of_nodes());
+    let mut queue = VecDeque::new();
+    for root in graph.node_range() {
+        if visited[root] {
+            continue;
+        }
+        visited[root] = true;
+        queue.push_back(root);
+        while let Some(node) = queue.pop_front() {
+            order.push(node);
+            for edge in graph.edge_range(node) {
+                let target = graph.target(edge);
+                if !visited[target] {
+                    visited[target] = true;
+                    queue.push_back(target);
+                }
+            }
+        }
+    }
+    order
+}
+
+/// Returns the nodes in the preorder of a depth-first traversal. Each
+/// traversal starts from the smallest node id that was not visited yet.
+pub fn depth_first_order<T: Ord + Copy>(graph: &StaticGraph<T>) -> Vec<NodeID> {
+    let mut visited = vec![false; graph.number_of_nodes()];
+    let mut order = Vec::with_capacity(graph.number_of_nodes());
+    let mut stack = Vec::new();
+    for root in graph.node_range() {
+        stack.push(root);
+        while let Some(node) = stack.pop() {
+            if visited[node] {
+                continue;
+            }
+            visited[node] = true;
+            order.push(node);
+            // push in reverse to visit the neighbors in the order of the edges
+            stack.extend(
+                graph
+                    .edge_range(node)
+                    .rev()
+                    .map(|edge| graph.target(edge))
+                    .filter(|target| !visited[*target]),
+            );
+        }
+    }
+    order
+}
+
+/// Returns the nodes ordered by the positions of their coordinates along the
+/// space-filling curve
+pub fn curve_order(coordinates: &[FPCoordinate], curve: Curve) -> Vec<NodeID> {
+    (0..coordinates.len())
+        .sorted_by(|a, b| curve.cmp(coordinates[*a], coordinates[*b]))
+        .collect()
+}
+
+/// Returns the nodes grouped by their cells. The cells are ordered by their
+/// ids, which keeps sibling cells next to each other, and nodes keep their
+/// relative order within a cell.
+pub fn partition_order(partition_ids: &[PartitionID]) -> Vec<NodeID> {
+    (0..partition_ids.len())
+        .sorted_by_key(|node| partition_ids[*node])
+        .collect()
+}
+
+/// Renumbers the nodes of the graph and its coordinates. Returns the permuted
+/// graph and coordinates together with the permutation from the old to the
+/// new node ids and back. The coordinates may be empty if the order does not
+/// depend on them.
+///
+/// # Arguments
+///
+/// * `graph` - the graph to be reordered
+/// * `coordinates` - the coordinates of the nodes of the graph
+/// * `order` - the order in which the nodes are numbered
+pub fn reorder<T: Ord + Copy>(
+    graph: &StaticGraph<T>,
+    coordinates: &[FPCoordinate],
+    order: NodeOrder,
+) -> (StaticGraph<T>, Vec<FPCoordinate>, Permutation) {
+    let order = match order {
+        NodeOrder::BreadthFirst => breadth_first_order(graph),
+        NodeOrder::DepthFirst => depth_first_order(graph),
+        NodeOrder::SpaceFillingCurve(curve) => {
+            assert_eq!(coordinates.len(), graph.number_of_nodes());
+            curve_order(coordinates, curve)
+        }
+        NodeOrder::Partition(partition_ids) => {
+            assert_eq!(partition_ids.len(), graph.number_of_nodes());
+            partition_order(partition_ids)
+        }
+    };
+    let permutation = Permutation::from_order(order);
+    let coordinates = if coordinates.is_empty() {
+        Vec::new()
+    } else {
+        permutation.apply(coordinates)
+    };
+    let graph = graph.permute(&permutation.forward, &permutation.backward);
+    (graph, coordinates, permutation)
+}
+
+#[cfg(test)]
+mod tests {
+    use itertools::Itertools;
+
+    use crate::{
+        edge::InputEdge,
+        geometry::primitives::FPCoordinate,
+        graph::Graph,
+        partition::PartitionID,
+        reordering::{breadth_first_order, depth_first_order, reorder, NodeOrder, Permutation},
+        space_filling_curve::Curve,
+        static_graph::StaticGraph,
+    };
+
+    fn graph() -> StaticGraph<i32> {
+        StaticGraph::new(vec![
+            InputEdge::new(0, 1, 3),
+            InputEdge::new(1, 2, 3),
+            InputEdge::new(4, 2, 1),
+            InputEdge::new(2, 3, 6),
+            InputEdge::new(0, 4, 2),
+            InputEdge::new(4, 5, 2),
+            InputEdge::new(5, 3, 7),
+            InputEdge::new(1, 5, 2),
+        ])
+    }
+
+    fn edges(graph: &StaticGraph<i32>, permutation: &Permutation) -> Vec<(usize, usize, i32)> {
+        graph
+            .node_range()
+            .flat_map(|node| {
+                graph.edge_range(node).map(move |edge| {
+                    (
+                        permutation.backward[node],
+                        permutation.backward[graph.target(edge)],
+                        *graph.data(edge),
+                    )
+                })
+            })
+            .sorted()
+            .collect_vec()
+    }
+
+    #[test]
+    fn traversal_orders() {
+        let graph = graph();
+        assert_eq!(breadth_first_order(&graph), vec![0, 1, 4, 2, 5, 3]);
+        assert_eq!(depth_first_order(&graph), vec![0, 1, 2, 3, 5, 4]);
+    }
+
+    #[test]
+    fn permutation_tables() {
+        let permutation = Permutation::from_order(vec![2, 0, 3, 1]);
+        assert_eq!(permutation.forward, vec![1, 3, 0, 2]);
+        assert_eq!(
+            permutation.apply(&['a', 'b', 'c', 'd']),
+            vec!['c', 'a', 'd', 'b']
+        );
+        assert_eq!(permutation.inverse().inverse(), permutation);
+        assert_eq!(
+            permutation
+                .inverse()
+                .apply(&permutation.apply(&[5, 6, 7, 8])),
+            vec![5, 6, 7, 8]
+        );
+    }
+
+    #[test]
+    fn reordered_graph_keeps_edges() {
+        let graph = graph();
+        let identity = edges(&graph, &Permutation::identity(6));
+        for order in [NodeOrder::BreadthFirst, NodeOrder::DepthFirst] {
+            let (permuted, coordinates, permutation) = reorder(&graph, &[], order);
+            assert!(coordinates.is_empty());
+            assert_eq!(permuted.number_of_nodes(), 6);
+            assert_eq!(edges(&permuted, &permutation), identity);
+        }
+    }
+
+    #[test]
+    fn isolated_nodes_are_kept() {
+        // node 3 has no outgoing edges and ends up last in the order
+        let graph = graph();
+        let permutation = Permutation::from_order(vec![0, 1, 2, 4, 5, 3]);
+        let permuted = graph.permute(&permutation.forward, &permutation.backward);
+        assert_eq!(permuted.number_of_nodes(), 6);
+        assert_eq!(permuted.out_degree(5), 0);
+        assert_eq!(
+            edges(&permuted, &permutation),
+            edges(&graph, &Permutation::identity(6))
+        );
+    }
+
+    #[test]
+    fn coordinate_and_partition_orders() {
+        let graph = graph();
+        let coordinates = [(1, 1), (0, 0), (3, 3), (1, 0), (2, 2), (0, 1)]
+            .iter()
+            .map(|(lat, lon)| FPCoordinate::new(*lat, *lon))
+            .collect_vec();
+        let (_, permuted, permutation) = reorder(
+            &graph,
+            &coordinates,
+            NodeOrder::SpaceFillingCurve(Curve::ZOrder),
+        );
+        assert_eq!(permutation.backward, vec![1, 5, 3, 0, 4, 2]);
+        assert_eq!(permuted[0], FPCoordinate::new(0, 0));
+
+        let partition_ids = [3, 2, 3, 2, 3, 2].map(PartitionID::new);
+        let (_, _, permutation) = reorder(&graph, &[], NodeOrder::Partition(&partition_ids));
+        assert_eq!(permutation.backward, vec![1, 3, 5, 0, 2, 4]);
+    }
+}
@@ -1,4 +1,9 @@
-use crate::geometry::primitives::FPCoordinate;
+use std::cmp::Ordering;
+
+use num::PrimInt;
+use rayon::prelude::*;
+
+use crate::{geometry::primitives::FPCoordinate, partition::GenericPartitionID};
 
 /// Provides a total order on fixed-point coordinates that corresponds to the
 /// well-known Z-order space-filling curve. The compiler will emit about twenty
@@ -12,4 +17,220 @@ pub fn zorder_cmp(lhs: FPCoordinate, rhs: FPCoordinate) -> std::cmp::Ordering {
 
 fn less_msb(x: i32, y: i32) -> bool {
     x < y && x < (x ^ y)
-}
\ No newline at end of file
+}
+
+/// Maps a signed coordinate to an unsigned one while preserving the order
+fn to_unsigned(value: i32) -> u32 {
+    (value as u32) ^ 0x8000_0000
+}
+
+fn to_signed(value: u32) -> i32 {
+    (value ^ 0x8000_0000) as i32
+}
+
+/// Computes the position of a fixed-point coordinate along the Hilbert curve
+/// that fills the whole 32x32 bit plane. Longitude is the x-axis and latitude
+/// the y-axis. Other than the Z-order curve, consecutive positions along the
+/// Hilbert curve are always adjacent, which gives better locality.
+pub fn hilbert_encode(coordinate: FPCoordinate) -> u64 {
+    let (mut x, mut y) = (to_unsigned(coordinate.lon), to_unsigned(coordinate.lat));
+    let mut key = 0;
+    let mut s = 1u32 << 31;
+    while s > 0 {
+        let rx = (x & s != 0) as u64;
+        let ry = (y & s != 0) as u64;
+        key += (s as u64) * (s as u64) * ((3 * rx) ^ ry);
+        // rotate the quadrant, such that the sub-curve has the base orientation
+        if ry == 0 {
+            if rx == 1 {
+                x = !x;
+                y = !y;
+            }
+            std::mem::swap(&mut x, &mut y);
+        }
+        s >>= 1;
+    }
+    key
+}
+
+/// Computes the fixed-point coordinate at a position along the Hilbert curve.
+/// This is the inverse of `hilbert_encode`.
+pub fn hilbert_decode(key: u64) -> FPCoordinate {
+    let (mut x, mut y) = (0u64, 0u64);
+    let mut t = key;
+    let mut s = 1u64;
+    while s < 1 << 32 {
+        let rx = 1 & (t / 2);
+        let ry = 1 & (t ^ rx);
+        if ry == 0 {
+            if rx == 1 {
+                x = s - 1 - x;
+                y = s - 1 - y;
+            }
+            std::mem::swap(&mut x, &mut y);
+        }
+        x += s * rx;
+        y += s * ry;
+        t /= 4;
+        s <<= 1;
+    }
+    FPCoordinate::new(to_signed(y as u32), to_signed(x as u32))
+}
+
+/// Provides a total order on fixed-point coordinates that corresponds to the
+/// Hilbert space-filling curve.
+pub fn hilbert_cmp(lhs: FPCoordinate, rhs: FPCoordinate) -> std::cmp::Ordering {
+    hilbert_encode(lhs).cmp(&hilbert_encode(rhs))
+}
+
+/// The space-filling curves along which coordinates can be ordered
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Curve {
+    ZOrder,
+    Hilbert,
+}
+
+impl Curve {
+    /// Compares two coordinates by their position along the curve
+    pub fn cmp(&self, lhs: FPCoordinate, rhs: FPCoordinate) -> Ordering {
+        match self {
+            Curve::ZOrder => zorder_cmp(lhs, rhs),
+            Curve::Hilbert => hilbert_cmp(lhs, rhs),
+        }
+    }
+}
+
+/// Partitions the nodes by sorting their coordinates along a space-filling
+/// curve and slicing the order into cells of at most the given size. The
+/// order is halved recursively, so the ids form the same hierarchy as those
+/// of a recursive bisection, and all cells are on the same level. This is a
+/// fast baseline to compare the cuts of other partitioners against.
+///
+/// # Arguments
+///
+/// * `coordinates` - the coordinates of the nodes
+/// * `curve` - the curve along which the nodes are ordered
+/// * `maximum_cell_size` - the maximum number of nodes of a cell
+pub fn partition<T: PrimInt + Send + Sync>(
+    coordinates: &[FPCoordinate],
+    curve: Curve,
+    maximum_cell_size: usize,
+) -> Vec<GenericPartitionID<T>> {
+    assert!(maximum_cell_size > 0, "cells must hold at least one node");
+    let number_of_nodes = coordinates.len();
+
+    // the shallowest level on which all cells are small enough
+    let mut level = 0;
+    while level < GenericPartitionID::<T>::MAXIMUM_LEVEL
+        && number_of_nodes.saturating_sub(1) >> level >= maximum_cell_size
+    {
+        level += 1;
+    }
+
+    let mut order = (0..number_of_nodes).collect::<Vec<_>>();
+    order.par_sort_unstable_by(|a, b| curve.cmp(coordinates[*a], coordinates[*b]));
+
+    // the i-th node of the order lies in cell floor(i * 2^level / n)
+    let mut partition_ids = vec![GenericPartitionID::root(); number_of_nodes];
+    for (position, node) in order.iter().enumerate() {
+        let cell = ((position as u128) << level) / number_of_nodes as u128;
+        let id = (T::one() << level as usize) | T::from(cell).unwrap();
+        partition_ids[*node] = GenericPartitionID::new(id);
+    }
+    partition_ids
+}
+
+#[cfg(test)]
+mod tests {
+    use itertools::Itertools;
+
+    use crate::{
+        geometry::primitives::FPCoordinate,
+        partition::{PartitionID, PartitionID64},
+        space_filling_curve::{hilbert_cmp, hilbert_decode, hilbert_encode, partition, Curve},
+    };
+
+    #[test]
+    fn hilbert_round_trip() {
+        for coordinate in [
+            FPCoordinate::new(0, 0),
+            FPCoordinate::new(-1, 1),
+            FPCoordinate::new(52_520_008, 13_404_954),
+            FPCoordinate::new(-33_868_820, 151_209_295),
+            FPCoordinate::min(),
+            FPCoordinate::max(),
+        ] {
+            assert_eq!(hilbert_decode(hilbert_encode(coordinate)), coordinate);
+        }
+        assert_eq!(hilbert_encode(FPCoordinate::min()), 0);
+    }
+
+    #[test]
+    fn hilbert_locality() {
+        // consecutive positions along the curve are adjacent coordinates
+        for key in (0..1000)
+            .chain(u64::MAX - 1000..u64::MAX)
+            .chain(1 << 40..(1 << 40) + 1000)
+        {
+            let (a, b) = (hilbert_decode(key), hilbert_decode(key + 1));
+            let distance =
+                (a.lat as i64 - b.lat as i64).abs() + (a.lon as i64 - b.lon as i64).abs();
+            assert_eq!(distance, 1);
+            assert_eq!(hilbert_cmp(a, b), std::cmp::Ordering::Less);
+        }
+    }
+
+    #[test]
+    fn hilbert_quadrants() {
+        // both curves visit the quadrants of a grid one after another
+        let coordinates = (0..16)
+            .map(|node| FPCoordinate::new(node / 4, node % 4))
+            .collect_vec();
+        let partition_ids: Vec<PartitionID> = partition(&coordinates, Curve::Hilbert, 4);
+        let cells = (0..16).into_group_map_by(|node| partition_ids[*node]);
+        assert_eq!(cells.len(), 4);
+        for nodes in cells.values() {
+            assert!(nodes.iter().all(|node| coordinates[*node].lat / 2
+                == coordinates[nodes[0]].lat / 2
+                && coordinates[*node].lon / 2 == coordinates[nodes[0]].lon / 2));
+        }
+    }
+
+    #[test]
+    fn zorder_quadrants() {
+        let coordinates = (0..16)
+            .map(|node| FPCoordinate::new(node / 4, node % 4))
+            .collect_vec();
+        let partition_ids: Vec<PartitionID> = partition(&coordinates, Curve::ZOrder, 4);
+        assert!(partition_ids.iter().all(|id| id.level() == 2));
+
+        // each cell is a quadrant of the grid
+        let cells = (0..16).into_group_map_by(|node| partition_ids[*node]);
+        assert_eq!(cells.len(), 4);
+        for nodes in cells.values() {
+            assert_eq!(nodes.len(), 4);
+            let (lat, lon) = (coordinates[nodes[0]].lat / 2, coordinates[nodes[0]].lon / 2);
+            assert!(
+                nodes
+                    .iter()
+                    .all(|node| coordinates[*node].lat / 2 == lat
+                        && coordinates[*node].lon / 2 == lon)
+            );
+        }
+    }
+
+    #[test]
+    fn cell_sizes() {
+        let coordinates = (0..1000)
+            .map(|node| FPCoordinate::new(node * 7 % 101, node * 13 % 97))
+            .collect_vec();
+        let partition_ids: Vec<PartitionID64> = partition(&coordinates, Curve::ZOrder, 100);
+        let cells = partition_ids.iter().counts();
+        assert_eq!(cells.len(), 16);
+        assert!(cells.values().all(|size| (62..=63).contains(size)));
+
+        // a single cell suffices for small inputs
+        let partition_ids: Vec<PartitionID> = partition(&coordinates, Curve::ZOrder, 1000);
+        assert!(partition_ids.iter().all(|id| *id == PartitionID::root()));
+    }
+}
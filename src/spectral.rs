@@ -0,0 +1,304 @@
+//! Spectral bisection for graphs without coordinates. The nodes are embedded
+//! on a line by an approximate Fiedler vector, i.e. the eigenvector of the
+//! second smallest eigenvalue of the graph Laplacian, which places nodes that
+//! are well connected close to each other. The resulting order replaces the
+//! projection onto an axis of inertial flow, and the cut is computed by the
+//! same contraction of both ends of the order into a source and a target.
+use std::{
+    collections::VecDeque,
+    sync::{atomic::AtomicI32, Arc},
+};
+
+use core::cmp::max;
+use itertools::Itertools;
+use log::debug;
+
+use crate::{
+    edge::{Edge, EdgeCapacity, InputEdge},
+    graph::{Graph, NodeID},
+    inertial_flow::{self, FlowResult},
+    renumbering_table::RenumberingTable,
+    static_graph::StaticGraph,
+};
+
+/// the power iteration stops after this many rounds if it did not converge
+const MAXIMUM_ITERATIONS: usize = 2000;
+/// the power iteration stops once an iterate moves less than this distance
+const TOLERANCE: f64 = 1e-7;
+
+/// Returns the hop distances of all nodes from the source. Unreachable nodes
+/// have distance zero.
+fn bfs_distances(graph: &StaticGraph<i32>, number_of_nodes: usize, source: NodeID) -> Vec<usize> {
+    let mut distances = vec![usize::MAX; number_of_nodes];
+    let mut queue = VecDeque::from([source]);
+    distances[source] = 0;
+    while let Some(node) = queue.pop_front() {
+        if node >= graph.number_of_nodes() {
+            continue;
+        }
+        for edge in graph.edge_range(node) {
+            let target = graph.target(edge);
+            if distances[target] == usize::MAX {
+                distances[target] = distances[node] + 1;
+                queue.push_back(target);
+            }
+        }
+    }
+    distances
+        .into_iter()
+        .map(|distance| if distance == usize::MAX { 0 } else { distance })
+        .collect()
+}
+
+/// Subtracts the mean and scales the vector to unit length. This removes the
+/// component along the constant eigenvector of the Laplacian.
+fn center_and_normalize(vector: &mut [f64]) {
+    let mean = vector.iter().sum::<f64>() / vector.len() as f64;
+    vector.iter_mut().for_each(|value| *value -= mean);
+    let norm = vector.iter().map(|value| value * value).sum::<f64>().sqrt();
+    if norm > 0. {
+        vector.iter_mut().for_each(|value| *value /= norm);
+    }
+}
+
+/// Computes an approximate Fiedler vector of the weighted Laplacian of an
+/// undirected graph by power iteration on `c*I - L`, where `c` bounds the
+/// largest eigenvalue of the Laplacian. The constant eigenvector is deflated
+/// in each round. The iteration starts from the hop distances to a
+/// peripheral node, which already resemble the Fiedler vector closely.
+///
+/// # Arguments
+///
+/// * `graph` - a symmetric graph whose edge data are the edge weights
+/// * `number_of_nodes` - the number of nodes, which includes isolated nodes
+///   that are beyond the largest node id of the graph
+pub fn fiedler_vector(graph: &StaticGraph<i32>, number_of_nodes: usize) -> Vec<f64> {
+    if number_of_nodes < 2 {
+        return vec![0.; number_of_nodes];
+    }
+    let degrees = (0..number_of_nodes)
+        .map(|node| {
+            if node < graph.number_of_nodes() {
+                graph
+                    .edge_range(node)
+                    .filter(|edge| graph.target(*edge) != node)
+                    .map(|edge| *graph.data(edge) as f64)
+                    .sum()
+            } else {
+                0.
+            }
+        })
+        .collect_vec();
+    let shift = 2. * degrees.iter().copied().fold(1., f64::max);
+
+    // a node that is farthest from an arbitrary node is peripheral
+    let distances = bfs_distances(graph, number_of_nodes, 0);
+    let (peripheral, _) = distances
+        .iter()
+        .enumerate()
+        .max_by_key(|(_, distance)| **distance)
+        .unwrap();
+    let mut vector = bfs_distances(graph, number_of_nodes, peripheral)
+        .into_iter()
+        .map(|distance| distance as f64)
+        .collect_vec();
+    center_and_normalize(&mut vector);
+
+    let mut next = vec![0.; number_of_nodes];
+    for iteration in 0..MAXIMUM_ITERATIONS {
+        // next = (c*I - L) * vector, where L = D - A
+        for node in 0..number_of_nodes {
+            let mut value = (shift - degrees[node]) * vector[node];
+            if node < graph.number_of_nodes() {
+                for edge in graph.edge_range(node) {
+                    let target = graph.target(edge);
+                    if target != node {
+                        value += *graph.data(edge) as f64 * vector[target];
+                    }
+                }
+            }
+            next[node] = value;
+        }
+        center_and_normalize(&mut next);
+
+        let distance = vector
+            .iter()
+            .zip(next.iter())
+            .map(|(a, b)| (a - b) * (a - b))
+            .sum::<f64>()
+            .sqrt();
+        std::mem::swap(&mut vector, &mut next);
+        if distance < TOLERANCE {
+            debug!("power iteration converged after {iteration} rounds");
+            break;
+        }
+    }
+    vector
+}
+
+/// Orders the nodes of the graph induced by the node id list along an
+/// approximate Fiedler vector of its Laplacian, whose edge weights are the
+/// capacities of the edges.
+///
+/// # Arguments
+///
+/// * `input_edges` - a list of edges that represents the input graph
+/// * `node_id_list` - list of node ids
+pub fn spectral_order<E: Edge<ID = NodeID> + EdgeCapacity>(
+    input_edges: &[E],
+    node_id_list: &[usize],
+) -> Vec<usize> {
+    let number_of_nodes = node_id_list.len();
+    let universe_size = input_edges.iter().fold(0, |acc, edge| {
+        max(acc, max(edge.source(), edge.target()) + 1)
+    });
+    let universe_size = node_id_list
+        .iter()
+        .fold(universe_size, |acc, id| max(acc, id + 1));
+
+    // nodes are numbered consecutively in the order of the node id list
+    let mut renumbering_table =
+        RenumberingTable::new_with_size_hint(universe_size, max(1, number_of_nodes));
+    for (local_id, id) in node_id_list.iter().enumerate() {
+        renumbering_table.set(*id, local_id);
+    }
+    let edges = input_edges
+        .iter()
+        .filter(|edge| {
+            renumbering_table.contains_key(edge.source())
+                && renumbering_table.contains_key(edge.target())
+        })
+        .flat_map(|edge| {
+            let (source, target) = (
+                renumbering_table.get(edge.source()),
+                renumbering_table.get(edge.target()),
+            );
+            // the Laplacian is defined on the undirected graph
+            [
+                InputEdge::new(source, target, edge.capacity()),
+                InputEdge::new(target, source, edge.capacity()),
+            ]
+        })
+        .collect_vec();
+    let graph = StaticGraph::new(edges);
+
+    let fiedler = fiedler_vector(&graph, number_of_nodes);
+    (0..number_of_nodes)
+        .sorted_by(|a, b| fiedler[*a].total_cmp(&fiedler[*b]))
+        .map(|local_id| node_id_list[local_id])
+        .collect_vec()
+}
+
+/// Computes the spectral bisection of the graph induced by the node id list.
+///
+/// # Arguments
+///
+/// * `input_edges` - a list of edges that represents the input graph, with
+///   their capacities
+/// * `node_id_list` - list of node ids
+/// * `balance_factor` - balance factor, i.e. how many nodes get contracted
+/// * `upper_bound` - a global upperbound to the best flow cut
+pub fn sub_step<E: Edge<ID = NodeID> + EdgeCapacity>(
+    input_edges: &[E],
+    node_id_list: &[usize],
+    balance_factor: f64,
+    upper_bound: Arc<AtomicI32>,
+) -> FlowResult {
+    sub_step_with_ratio(input_edges, node_id_list, balance_factor, 0.5, upper_bound)
+}
+
+/// Computes the spectral bisection of the graph induced by the node id list
+/// that targets an uneven split.
+///
+/// # Arguments
+///
+/// * `input_edges` - a list of edges that represents the input graph, with
+///   their capacities
+/// * `node_id_list` - list of node ids
+/// * `balance_factor` - balance factor, i.e. how many nodes get contracted
+/// * `ratio` - the targeted share of nodes on the left side of the cut
+/// * `upper_bound` - a global upperbound to the best flow cut
+pub fn sub_step_with_ratio<E: Edge<ID = NodeID> + EdgeCapacity>(
+    input_edges: &[E],
+    node_id_list: &[usize],
+    balance_factor: f64,
+    ratio: f64,
+    upper_bound: Arc<AtomicI32>,
+) -> FlowResult {
+    debug!("computing spectral order of {} nodes", node_id_list.len());
+    let node_id_list = spectral_order(input_edges, node_id_list);
+    inertial_flow::ordered_sub_step(
+        input_edges,
+        &node_id_list,
+        balance_factor,
+        ratio,
+        upper_bound,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use itertools::Itertools;
+    use std::sync::{atomic::AtomicI32, Arc};
+
+    use crate::{
+        edge::{InputEdge, TrivialEdge},
+        spectral::{spectral_order, sub_step},
+        test_utils::grid,
+    };
+
+    #[test]
+    fn path_order() {
+        // a path is ordered from one end to the other by its Fiedler vector
+        let edges = [3, 0, 4, 1, 2]
+            .iter()
+            .tuple_windows()
+            .map(|(u, v)| TrivialEdge {
+                source: *u,
+                target: *v,
+            })
+            .collect_vec();
+        let order = spectral_order(&edges, &(0..5).collect_vec());
+        assert!(order == vec![3, 0, 4, 1, 2] || order == vec![2, 1, 4, 0, 3]);
+    }
+
+    #[test]
+    fn weighted_order() {
+        // a cycle whose two light edges are cut by the embedding
+        let edges = [
+            (0, 1, 10),
+            (1, 2, 10),
+            (2, 3, 1),
+            (3, 4, 10),
+            (4, 5, 10),
+            (5, 0, 1),
+        ]
+        .iter()
+        .map(|(u, v, weight)| InputEdge::new(*u, *v, *weight))
+        .collect_vec();
+        let order = spectral_order(&edges, &(0..6).collect_vec());
+        let halves = [
+            order[0..3].iter().sorted().copied().collect_vec(),
+            order[3..6].iter().sorted().copied().collect_vec(),
+        ];
+        assert!(
+            halves == [vec![0, 1, 2], vec![3, 4, 5]] || halves == [vec![3, 4, 5], vec![0, 1, 2]]
+        );
+    }
+
+    #[test]
+    fn grid_bisection() {
+        // the elongated grid is cut across its short side
+        let edges = grid(32, 8);
+        let node_id_list = (0..256).collect_vec();
+        let result = sub_step(
+            &edges,
+            &node_id_list,
+            0.25,
+            Arc::new(AtomicI32::new(i32::MAX)),
+        );
+        assert_eq!(result.flow, 8);
+        assert_eq!(result.left_ids.len() + result.right_ids.len(), 256);
+        assert!(result.balance >= 0.25);
+    }
+}
@@ -87,6 +87,36 @@ impl<T: Ord + Copy> StaticGraph<T> {
         debug_assert!(graph.check_integrity());
         graph
     }
+
+    /// Returns a copy of the graph whose nodes are renumbered, where `forward`
+    /// holds the new id of each old id and `backward` the old id of each new
+    /// id, e.g. of a `reordering::Permutation`. The number of nodes is kept,
+    /// even if the last nodes of the new order have no edges.
+    pub fn permute(&self, forward: &[NodeID], backward: &[NodeID]) -> Self {
+        assert_eq!(forward.len(), self.number_of_nodes());
+        assert_eq!(backward.len(), self.number_of_nodes());
+        let mut graph = Self::default();
+        graph.node_array.reserve(self.node_array.len());
+        graph.edge_array.reserve(self.edge_array.len());
+        graph.node_array.push(NodeArrayEntry::new(0));
+        for node in backward {
+            let first_edge = graph.edge_array.len();
+            graph
+                .edge_array
+                .extend(self.edge_range(*node).map(|edge| EdgeArrayEntry {
+                    target: forward[self.target(edge)],
+                    data: *self.data(edge),
+                }));
+            // keep the adjacency sorted as if the graph was built from scratch
+            graph.edge_array[first_edge..]
+                .sort_by(|a, b| a.target.cmp(&b.target).then(a.data.cmp(&b.data)));
+            graph
+                .node_array
+                .push(NodeArrayEntry::new(graph.edge_array.len()));
+        }
+        debug_assert!(graph.check_integrity());
+        graph
+    }
 }
 
 impl<T: Ord + Copy> Graph<T> for StaticGraph<T> {
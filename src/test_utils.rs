@@ -0,0 +1,95 @@
+//! Builders of small inputs that are shared by the tests of several modules
+use std::path::PathBuf;
+
+use itertools::Itertools;
+
+use crate::{
+    edge::{InputEdge, TrivialEdge},
+    geometry::primitives::FPCoordinate,
+    max_flow::ResidualCapacity,
+};
+
+/// Returns the edges of a grid of the given width and height in both
+/// directions. Nodes are numbered row by row.
+pub fn grid(width: usize, height: usize) -> Vec<TrivialEdge> {
+    let mut edges = Vec::new();
+    for row in 0..height {
+        for column in 0..width {
+            let node = width * row + column;
+            if column + 1 < width {
+                edges.push((node, node + 1));
+            }
+            if row + 1 < height {
+                edges.push((node, node + width));
+            }
+        }
+    }
+    edges
+        .iter()
+        .flat_map(|(u, v)| {
+            [
+                TrivialEdge {
+                    source: *u,
+                    target: *v,
+                },
+                TrivialEdge {
+                    source: *v,
+                    target: *u,
+                },
+            ]
+        })
+        .collect_vec()
+}
+
+/// Returns the edges of two square grids of the given side length in both
+/// directions, which are connected by a single edge between the last node of
+/// the first and the first node of the second
+pub fn dumbbell(side: usize) -> Vec<TrivialEdge> {
+    let size = side * side;
+    let mut edges = grid(side, side);
+    let offset = edges.clone().into_iter().map(|edge| TrivialEdge {
+        source: edge.source + size,
+        target: edge.target + size,
+    });
+    edges.extend(offset);
+    edges.push(TrivialEdge {
+        source: size - 1,
+        target: size,
+    });
+    edges.push(TrivialEdge {
+        source: size,
+        target: size - 1,
+    });
+    edges
+}
+
+/// Returns the coordinates of the nodes of a grid, which are 1000 units apart
+pub fn grid_coordinates(width: usize, height: usize) -> Vec<FPCoordinate> {
+    (0..height)
+        .cartesian_product(0..width)
+        .map(|(row, column)| FPCoordinate::new(1000 * row as i32, 1000 * column as i32))
+        .collect_vec()
+}
+
+/// Returns the max-flow instance from Cormen, Leiserson, Rivest and Stein,
+/// whose maximum flow from node 0 to node 5 is 23
+pub fn clr_edges() -> Vec<InputEdge<ResidualCapacity>> {
+    vec![
+        InputEdge::new(0, 1, ResidualCapacity::new(16)),
+        InputEdge::new(0, 2, ResidualCapacity::new(13)),
+        InputEdge::new(1, 2, ResidualCapacity::new(10)),
+        InputEdge::new(1, 3, ResidualCapacity::new(12)),
+        InputEdge::new(2, 1, ResidualCapacity::new(4)),
+        InputEdge::new(2, 4, ResidualCapacity::new(14)),
+        InputEdge::new(3, 2, ResidualCapacity::new(9)),
+        InputEdge::new(3, 5, ResidualCapacity::new(20)),
+        InputEdge::new(4, 3, ResidualCapacity::new(7)),
+        InputEdge::new(4, 5, ResidualCapacity::new(4)),
+    ]
+}
+
+/// Returns a path in the temporary directory that is unique to the running
+/// tests, so names only need to differ between the tests themselves
+pub fn temp_file(name: &str) -> PathBuf {
+    std::env::temp_dir().join(format!("toolbox_rs_{}_{name}", std::process::id()))
+}